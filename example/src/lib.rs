@@ -1,6 +1,10 @@
 //! Example (misère) _Nim_ game for showing how to use the wrapper library.
 
-use surena_game::*;
+use surena_game::{
+    options::Options,
+    serialize::{deserialize, Serialize},
+    *,
+};
 
 use std::fmt::Write;
 
@@ -9,6 +13,19 @@ type Counter = u16;
 const DEFAULT_COUNTER: Counter = 21;
 const DEFAULT_MAX_SUB: Counter = 3;
 
+game_options! {
+    /// The options of _Nim_, e.g., "21 3".
+    ///
+    /// Parsing, exporting, and sizing is generated by the macro.
+    struct NimOptions {
+        /// Starting value of the counter.
+        counter: Counter = DEFAULT_COUNTER,
+        /// Maximum number which can be subtracted in one move.
+        #[range(1, Counter::MAX)]
+        max_sub: Counter = DEFAULT_MAX_SUB,
+    }
+}
+
 /// This struct contains the game data.
 ///
 /// It acts as the `Self` for the surena API calls.
@@ -30,53 +47,6 @@ impl Nim {
         }
     }
 
-    fn from_options(opts: &str) -> Result<Self> {
-        // eg. "21 3"
-        let mut split = opts.split_whitespace();
-
-        let counter = match split.next() {
-            None => {
-                // Remember to include a trailing NUL byte for static errors!
-                return Err(Error::new_static(
-                    ErrorCode::InvalidInput,
-                    "missing starting counter\0",
-                ));
-            }
-            Some(c) => c,
-        };
-        let counter = counter.parse().map_err(|e| {
-            // Errors can be nicely handled using new_dynamic and format!().
-            Error::new_dynamic(
-                ErrorCode::InvalidInput,
-                format!("counter parsing error: {e}"),
-            )
-        })?;
-
-        let max_sub = match split.next() {
-            None => {
-                return Err(Error::new_static(
-                    ErrorCode::InvalidInput,
-                    "missing maximum subtrahend\0",
-                ))
-            }
-            Some(s) => s,
-        };
-        let max_sub = max_sub.parse().map_err(|e| {
-            Error::new_dynamic(
-                ErrorCode::InvalidInput,
-                format!("subtrahend parsing error: {e}"),
-            )
-        })?;
-        if max_sub == 0 {
-            return Err(Error::new_static(
-                ErrorCode::InvalidOptions,
-                "maximum subtrahend is zero\0",
-            ));
-        }
-
-        Ok(Nim::new(counter, max_sub))
-    }
-
     /// This calculates the [`buf_sizer`] according to the description in the
     /// `game.h`.
     ///
@@ -85,13 +55,15 @@ impl Nim {
         // eg. "A 42\0"
         let state_str = digits(self.counter) + 3;
         buf_sizer {
-            options_str: digits(self.counter) + digits(self.max_sub) + 2,
+            options_str: NimOptions::options_str(),
             state_str,
             player_count: 2,
             max_players_to_move: 1,
             max_moves: self.max_sub.into(),
             max_results: 1,
             move_str: digits(self.max_sub) + 1,
+            // counter, max_sub, initial_counter, and turn
+            serialize: 3 * std::mem::size_of::<Counter>() + 1,
             print_str: state_str + 1,
             ..Default::default()
         }
@@ -146,18 +118,28 @@ impl GameMethods for Nim {
                         "legacy not supported",
                     ));
                 }
-                let mut g = opts
-                    .map(Self::from_options)
-                    .transpose()?
-                    .unwrap_or_default();
+                let options = NimOptions::from_opts(*opts)?;
+                let mut g = Nim::new(options.counter, options.max_sub);
                 g.import_state(*state)?;
                 g
             }
-            GameInit::Serialized(_) => {
-                return Err(Error::new_static(
-                    ErrorCode::FeatureUnsupported,
-                    "initialization via serialized state unsupported",
-                ))
+            GameInit::Serialized(bytes) => {
+                // This is the inverse of Nim::serialize.
+                let (counter, max_sub, initial_counter, turn) = deserialize(bytes)?;
+                if max_sub == 0 {
+                    return Err(Error::new_static(
+                        ErrorCode::InvalidInput,
+                        "maximum subtrahend is zero\0",
+                    ));
+                }
+                // Serialized data is validated like an imported state.
+                counter_too_large(counter, initial_counter)?;
+                Nim {
+                    counter,
+                    max_sub,
+                    initial_counter,
+                    turn,
+                }
             }
         };
 
@@ -167,12 +149,23 @@ impl GameMethods for Nim {
 
     /// Export the original game settings used to create the game.
     ///
-    /// An [`StrBuf`] can be written to by simply using [`write!()`].
+    /// Options declared with [`game_options!`] can simply be exported.
+    /// Otherwise, an [`StrBuf`] can be written to by using [`write!()`].
     /// The written length must not exceed [`buf_sizer::options_str`]` - 1`.
     fn export_options(&mut self, str_buf: &mut StrBuf) -> Result<()> {
-        write!(str_buf, "{} {}", self.initial_counter, self.max_sub)
-            .expect("failed to write options buffer");
-        Ok(())
+        let options = NimOptions {
+            counter: self.initial_counter,
+            max_sub: self.max_sub,
+        };
+        options.export(str_buf)
+    }
+
+    /// Write the complete game data in binary form.
+    ///
+    /// A [`PtrVec<u8>`] can be written to using the
+    /// [`Serialize`](surena_game::serialize::Serialize) trait.
+    fn serialize(&mut self, buf: &mut PtrVec<u8>) -> Result<()> {
+        (self.counter, self.max_sub, self.initial_counter, self.turn).serialize(buf)
     }
 
     /// Simply copy the data from `other` to `self`.
@@ -205,10 +198,11 @@ impl GameMethods for Nim {
         };
         let counter = match split.next() {
             None => {
+                // Remember to include a trailing NUL byte for static errors!
                 return Err(Error::new_static(
                     ErrorCode::InvalidInput,
                     "missing counter value\0",
-                ))
+                ));
             }
             Some(c) => c,
         };
@@ -223,12 +217,14 @@ impl GameMethods for Nim {
                 ))
             }
         };
-        self.counter = counter.parse().map_err(|e| {
+        let counter = counter.parse().map_err(|e| {
             Error::new_dynamic(
                 ErrorCode::InvalidInput,
                 format!("counter parsing error: {e}"),
             )
         })?;
+        counter_too_large(counter, self.initial_counter)?;
+        self.counter = counter;
 
         Ok(())
     }
@@ -274,7 +270,13 @@ impl GameMethods for Nim {
         Ok(())
     }
 
-    fn is_legal_move(&mut self, player: player_id, mov: move_code) -> Result<()> {
+    /// _Nim_ has no simultaneous moves, so `sync_ctr` can be ignored.
+    fn is_legal_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        _sync_ctr: sync_counter,
+    ) -> Result<()> {
         if self.counter == 0 {
             return Err(Error::new_static(
                 ErrorCode::InvalidInput,
@@ -297,7 +299,12 @@ impl GameMethods for Nim {
         Ok(())
     }
 
-    fn make_move(&mut self, _player: player_id, mov: move_code) -> Result<()> {
+    fn make_move(
+        &mut self,
+        _player: player_id,
+        mov: move_code,
+        _sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
         self.counter -= mov as Counter;
         self.turn = !self.turn;
         Ok(())
@@ -310,6 +317,11 @@ impl GameMethods for Nim {
         Ok(())
     }
 
+    fn get_id(&mut self) -> Result<u64> {
+        // The state is small enough to be used as id directly.
+        Ok(u64::from(self.counter) << 1 | u64::from(self.turn))
+    }
+
     fn get_move_code(&mut self, _player: player_id, string: &str) -> Result<move_code> {
         let mov: Counter = string.parse().map_err(|e| {
             Error::new_dynamic(ErrorCode::InvalidInput, format!("move parsing error: {e}"))
@@ -338,14 +350,22 @@ impl GameMethods for Nim {
 /// This function creates the [`game_methods`] struct for exporting _Nim_.
 ///
 /// It uses the provided [`create_game_methods()`] function.
+pub fn example_game_methods() -> game_methods {
+    create_game_methods::<Nim>(metadata())
+}
+
 /// [`game_feature_flags`] need to be set via the `set_` functions.
 /// Remember to add the trailing NUL byte to the `_name`s (see [`cstr()`]).
-fn example_game_methods() -> game_methods {
+fn metadata() -> Metadata {
     let mut features = game_feature_flags::default();
     features.set_print(true);
     features.set_options(true);
+    features.set_serializable(true);
+    features.set_id(true);
+    // The default playout implementation can be used for every game.
+    features.set_playout(true);
 
-    create_game_methods::<Nim>(Metadata {
+    Metadata {
         game_name: cstr("Nim\0"),
         variant_name: cstr("Standard\0"),
         impl_name: cstr("surena_game_rs\0"),
@@ -355,7 +375,7 @@ fn example_game_methods() -> game_methods {
             patch: 0,
         },
         features,
-    })
+    }
 }
 
 fn sub_too_large(mov: Counter, max: Counter) -> Result<()> {
@@ -369,6 +389,19 @@ fn sub_too_large(mov: Counter, max: Counter) -> Result<()> {
     }
 }
 
+/// The counter never increases, so the [`buf_sizer`] only fits counters up to
+/// the starting value.
+fn counter_too_large(counter: Counter, initial: Counter) -> Result<()> {
+    if counter > initial {
+        Err(Error::new_dynamic(
+            ErrorCode::InvalidInput,
+            format!("counter can be at most {initial}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 /// Calculates the number of digits needed to print `n`.
 const fn digits(mut n: Counter) -> usize {
     let mut digits = 1;
@@ -384,3 +417,175 @@ const fn digits(mut n: Counter) -> usize {
 // Finally, this macro creates the required plugin_get_game_methods function,
 // which exports all provided game_methods structs to surena.
 plugin_get_game_methods!(example_game_methods());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use surena_game::{
+        ai::alpha_beta,
+        playout,
+        ptr_vec::Storage,
+        record::{Record, Recorder},
+        sizer::{self, Exploration},
+        testing,
+    };
+
+    fn create(opts: &str) -> (Nim, buf_sizer) {
+        let init_info = GameInit::Standard {
+            opts: Some(opts),
+            legacy: None,
+            state: None,
+        };
+        Nim::create(&init_info).expect("failed to create Nim")
+    }
+
+    #[test]
+    fn playout_winners_match_results() {
+        let (game, sizer) = create("21 3");
+        let mut winners = Storage::new(sizer.max_results.into());
+        let mut results = Storage::new(sizer.max_results.into());
+        for seed in 0..50 {
+            let mut game = game;
+            let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+            let over = game
+                .playout(
+                    seed,
+                    &sizer,
+                    playout::MAX_MOVES,
+                    &mut sync_ctr,
+                    Some(&mut winners.get_ptr_vec()),
+                )
+                .expect("playout failed");
+            assert!(over);
+            game.get_results(&mut results.get_ptr_vec())
+                .expect("get_results failed");
+            assert_eq!(1, winners.len());
+            assert_eq!(*results, *winners);
+        }
+    }
+
+    #[test]
+    fn playout_stops_at_max_moves() {
+        let (mut game, sizer) = create("21 3");
+        let mut winners = Storage::new(sizer.max_results.into());
+        let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+        let over = game
+            .playout(
+                0,
+                &sizer,
+                3,
+                &mut sync_ctr,
+                Some(&mut winners.get_ptr_vec()),
+            )
+            .expect("playout failed");
+        assert!(!over);
+        assert!(winners.is_empty());
+        assert!(game.counter > 0);
+    }
+
+    #[test]
+    fn counter_cannot_exceed_start() {
+        let (mut game, sizer) = create("21 3");
+        assert!(game.import_state(Some("B 22")).is_err());
+        game.import_state(Some("B 21")).expect("import failed");
+
+        let mut bytes = Storage::new(sizer.serialize);
+        game.serialize(&mut bytes.get_ptr_vec())
+            .expect("serialization failed");
+        assert!(Nim::create(&GameInit::Serialized(&bytes)).is_ok());
+        game.counter = 22;
+        game.serialize(&mut bytes.get_ptr_vec())
+            .expect("serialization failed");
+        assert!(Nim::create(&GameInit::Serialized(&bytes)).is_err());
+    }
+
+    #[test]
+    fn conformance() {
+        let init_info = GameInit::Default;
+        let violations = testing::check::<Nim>(metadata(), &init_info, &Default::default());
+        let messages: Vec<_> = violations.iter().map(ToString::to_string).collect();
+        assert!(violations.is_empty(), "{}", messages.join("\n"));
+    }
+
+    #[test]
+    fn sizer_matches_observed() {
+        let init_info = GameInit::Standard {
+            opts: Some("21 3"),
+            legacy: None,
+            state: None,
+        };
+        let report =
+            sizer::explore::<Nim>(&init_info, metadata().features, &Exploration::default())
+                .expect("exploration failed");
+        assert!(report.complete);
+        assert!(report.too_small().is_empty(), "{report}");
+        // The options size covers all options, the others are exact.
+        let (declared, observed) = (&report.declared, &report.observed);
+        assert_eq!(declared.state_str, observed.state_str);
+        assert_eq!(declared.max_players_to_move, observed.max_players_to_move);
+        assert_eq!(declared.max_moves, observed.max_moves);
+        assert_eq!(declared.max_results, observed.max_results);
+        assert_eq!(declared.move_str, observed.move_str);
+        assert_eq!(declared.print_str, observed.print_str);
+    }
+
+    #[test]
+    fn alpha_beta_solves_nim() {
+        let mut without_id = metadata().features;
+        without_id.set_id(false);
+        for counter in [21, 22, 5, 1, 2] {
+            let (mut game, sizer) = create(&format!("{counter} 3"));
+            let mut scores = vec![];
+            for features in [metadata().features, without_id] {
+                let mut search = alpha_beta::AlphaBeta::new(&sizer, features, Default::default());
+                let analysis = search.search(&mut game).expect("search failed");
+                assert!(analysis.solved);
+                // Taking the last counter loses, so 4n + 1 counters are lost.
+                assert_eq!(counter % 4 == 1, analysis.score < 0.0, "counter {counter}");
+                scores.push(analysis.score);
+            }
+            assert_eq!(scores[0], scores[1]);
+        }
+    }
+
+    /// Records `moves` played from the options `opts`.
+    fn record(opts: &str, moves: &[move_code]) -> (Nim, Record) {
+        let (mut game, sizer) = create(opts);
+        let mut recorder = Recorder::new(&metadata(), &mut game, &sizer).expect("recording failed");
+        let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+        for &mov in moves {
+            let player = game.player_id();
+            recorder
+                .make_move(&mut game, player, mov, &mut sync_ctr)
+                .expect("recording failed");
+        }
+        (game, recorder.into_record())
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let (game, record) = record("21 3", &[3, 1, 2, 3]);
+        let parsed: Record = record.to_string().parse().expect("parsing failed");
+        assert_eq!(record, parsed);
+        let (replayed, _) = parsed.replay::<Nim>(&metadata()).expect("replay failed");
+        assert!(game == replayed);
+    }
+
+    #[test]
+    fn replay_rejects_invalid_records() {
+        let (_, record) = record("21 3", &[3]);
+
+        let mut right_player = record.clone();
+        right_player.moves.push((2, "1".to_string()));
+        assert!(right_player.replay::<Nim>(&metadata()).is_ok());
+        let mut wrong_player = record.clone();
+        wrong_player.moves.push((1, "1".to_string()));
+        assert!(wrong_player.replay::<Nim>(&metadata()).is_err());
+
+        let wrong_name = Record {
+            game: "Chess".to_string(),
+            ..record
+        };
+        assert!(wrong_name.replay::<Nim>(&metadata()).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Adapter for searching any [`Game`] with the [`ai`](surena_game::ai)
+//! module.
+
+use surena_game::{ai::State, host::Game, move_code, player_id, sync_counter, PtrVec, Result};
+
+/// A [`Game`] implementing [`State`] by calling its
+/// [`game_methods`](surena_game::game_methods).
+pub struct Hosted<'m>(pub Game<'m>);
+
+impl State for Hosted<'_> {
+    fn try_clone(&mut self) -> Result<Self> {
+        self.0.try_clone().map(Self)
+    }
+
+    fn copy_state(&mut self, other: &mut Self) -> Result<()> {
+        self.0.copy_from(&mut other.0)
+    }
+
+    fn movers(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        players.extend_from_slice(self.0.players_to_move()?);
+        Ok(())
+    }
+
+    fn moves(&mut self, player: player_id, moves: &mut PtrVec<move_code>) -> Result<()> {
+        moves.extend_from_slice(self.0.get_concrete_moves(player)?);
+        Ok(())
+    }
+
+    fn play(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        _sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
+        self.0.make_move(player, mov)
+    }
+
+    fn winners(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        players.extend_from_slice(self.0.get_results()?);
+        Ok(())
+    }
+
+    fn random_move(&mut self, seed: u64) -> Result<move_code> {
+        self.0.get_random_move(seed)
+    }
+}
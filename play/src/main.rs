@@ -0,0 +1,477 @@
+//! `surena-play` plays _surena_ games in the terminal without the C toolchain.
+//!
+//! Games are either linked in (currently the example _Nim_) or loaded from a
+//! plugin library via `--plugin`.
+//! Every player is controlled by a human, uniformly random moves, or
+//! [MCTS](surena_game::ai::mcts).
+//! See [`USAGE`] for all arguments.
+
+mod hosted;
+
+use hosted::Hosted;
+use surena_game::{
+    ai::{
+        mcts::{self, Mcts},
+        Budget,
+    },
+    describe, game_methods,
+    host::{Game, Plugin},
+    move_code, player_id,
+    rng::Rng,
+    GameInit, PLAYER_RAND,
+};
+
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    io::{self, BufRead, Write},
+    os::raw::c_char,
+    process::ExitCode,
+};
+
+const USAGE: &str = "\
+Usage: surena-play [OPTIONS]
+
+Options:
+  --plugin <PATH>        Load the games of a plugin library (repeatable)
+  --game <NAME>          Play the game <GAME>[.<VARIANT>[.<IMPL>]]
+  --options <OPTIONS>    Create the game with these options
+  --state <STATE>        Start from this state
+  --player <ID>=<KIND>   Control player <ID> by human (default), random, or
+                         mcts[:<ITERATIONS>] (repeatable)
+  --seed <SEED>          Seed for random and MCTS players [default: 0]
+  --list                 List all available games
+  --help                 Print this help
+
+Human players enter moves or one of the commands:
+  moves                  List all legal moves
+  quit                   Stop playing
+";
+
+/// Controller of a player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Human,
+    Random,
+    Mcts(Budget),
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    plugins: Vec<String>,
+    game: Option<String>,
+    options: Option<String>,
+    state: Option<String>,
+    players: HashMap<player_id, Kind>,
+    seed: u64,
+    list: bool,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns [`None`] if the help is requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--plugin" => parsed.plugins.push(value()?),
+            "--game" => parsed.game = Some(value()?),
+            "--options" => parsed.options = Some(value()?),
+            "--state" => parsed.state = Some(value()?),
+            "--player" => {
+                let (player, kind) = parse_player(&value()?)?;
+                parsed.players.insert(player, kind);
+            }
+            "--seed" => {
+                let seed = value()?;
+                parsed.seed = seed
+                    .parse()
+                    .map_err(|e| format!("invalid seed {seed}: {e}"))?;
+            }
+            "--list" => parsed.list = true,
+            "--help" | "-h" => return Ok(None),
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Parses `<ID>=<KIND>`.
+fn parse_player(spec: &str) -> Result<(player_id, Kind), String> {
+    let (player, kind) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected <ID>=<KIND> but got {spec}"))?;
+    let player = player
+        .parse()
+        .ok()
+        .filter(|&p| p != 0 && p != PLAYER_RAND)
+        .ok_or_else(|| format!("invalid player {player}"))?;
+    let kind = match kind.split_once(':') {
+        None if kind == "human" => Kind::Human,
+        None if kind == "random" => Kind::Random,
+        None if kind == "mcts" => Kind::Mcts(Budget::default()),
+        Some(("mcts", iterations)) => Kind::Mcts(Budget {
+            iterations: Some(
+                iterations
+                    .parse()
+                    .map_err(|e| format!("invalid iterations {iterations}: {e}"))?,
+            ),
+            time: None,
+        }),
+        _ => return Err(format!("unknown player kind {kind}")),
+    };
+    Ok((player, kind))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let builtin = [example::example_game_methods()];
+    let plugins = args
+        .plugins
+        .iter()
+        .map(|path| {
+            // Loading a plugin runs its code, which the user asked for.
+            unsafe { Plugin::load(path) }.map_err(|e| format!("{path}: {}", describe(&e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut available: Vec<&game_methods> = vec![];
+    for methods in builtin
+        .iter()
+        .chain(plugins.iter().flat_map(Plugin::methods))
+    {
+        // Plugins replace linked-in games of the same name.
+        available.retain(|&m| name(m) != name(methods));
+        available.push(methods);
+    }
+
+    if args.list {
+        for methods in available {
+            let version = methods.version;
+            println!(
+                "{} ({}.{}.{})",
+                name(methods),
+                version.major,
+                version.minor,
+                version.patch
+            );
+        }
+        return Ok(());
+    }
+
+    let methods = select(&available, args.game.as_deref())?;
+    let init_info = if args.options.is_some() || args.state.is_some() {
+        GameInit::Standard {
+            opts: args.options.as_deref(),
+            legacy: None,
+            state: args.state.as_deref(),
+        }
+    } else {
+        GameInit::Default
+    };
+    let mut game = Game::new(methods, &init_info).map_err(|e| describe(&e))?;
+    play(&mut game, args)
+}
+
+/// Selects the game matching `query` or the only available one.
+fn select<'m>(
+    available: &[&'m game_methods],
+    query: Option<&str>,
+) -> Result<&'m game_methods, String> {
+    let matching: Vec<_> = available
+        .iter()
+        .copied()
+        .filter(|&m| match query {
+            Some(q) => name(m) == q || name(m).starts_with(&format!("{q}.")),
+            None => true,
+        })
+        .collect();
+    match (matching.as_slice(), query) {
+        ([methods], _) => Ok(methods),
+        ([], Some(query)) => Err(format!("no game matches {query}, see --list")),
+        (_, Some(query)) => Err(format!("multiple games match {query}, see --list")),
+        (_, None) => Err("multiple games are available, choose one via --game".to_string()),
+    }
+}
+
+fn play(game: &mut Game, args: &Args) -> Result<(), String> {
+    let mut rng = Rng::new(args.seed);
+    let mut input = io::stdin().lock();
+
+    loop {
+        if game.methods().features.print() {
+            print!("{}", game.print().map_err(|e| describe(&e))?);
+        } else {
+            println!("{}", game.export_state().map_err(|e| describe(&e))?);
+        }
+
+        let players = game.players_to_move().map_err(|e| describe(&e))?.to_vec();
+        if players.is_empty() {
+            let winners = game.get_results().map_err(|e| describe(&e))?;
+            println!("{}", results(winners));
+            return Ok(());
+        }
+
+        // With simultaneous moves, every player to move makes a move.
+        for player in players {
+            let kind = if player == PLAYER_RAND {
+                Kind::Random
+            } else {
+                args.players.get(&player).copied().unwrap_or(Kind::Human)
+            };
+            let Some(mov) = choose(game, player, kind, &mut rng, &mut input)? else {
+                return Ok(());
+            };
+
+            if kind != Kind::Human {
+                let string = game.get_move_str(player, mov).map_err(|e| describe(&e))?;
+                println!("player {player} plays {string}");
+            }
+            game.make_move(player, mov).map_err(|e| describe(&e))?;
+        }
+    }
+}
+
+/// Returns the move of `player` controlled by `kind`.
+///
+/// Returns [`None`] if a human player quits.
+fn choose(
+    game: &mut Game,
+    player: player_id,
+    kind: Kind,
+    rng: &mut Rng,
+    input: &mut impl BufRead,
+) -> Result<Option<move_code>, String> {
+    let mov = match kind {
+        Kind::Human => return read_move(game, player, input),
+        Kind::Random if player == PLAYER_RAND && game.methods().features.random_moves() => game
+            .get_random_move(rng.next_u64())
+            .map_err(|e| describe(&e))?,
+        Kind::Random => {
+            let moves = game.get_concrete_moves(player).map_err(|e| describe(&e))?;
+            if moves.is_empty() {
+                return Err(format!("player {player} has no moves"));
+            }
+            moves[rng.index(moves.len())]
+        }
+        Kind::Mcts(budget) => search(game, budget, rng.next_u64())?,
+    };
+    Ok(Some(mov))
+}
+
+/// Prompts until a legal move is entered.
+///
+/// Returns [`None`] if the input ends or the player quits.
+fn read_move(
+    game: &mut Game,
+    player: player_id,
+    input: &mut impl BufRead,
+) -> Result<Option<move_code>, String> {
+    let mut line = String::new();
+    loop {
+        print!("player {player}> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        line.clear();
+        if input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            println!();
+            return Ok(None);
+        }
+
+        match line.trim() {
+            "" => continue,
+            "quit" => return Ok(None),
+            "moves" => {
+                let moves = game
+                    .get_concrete_moves(player)
+                    .map_err(|e| describe(&e))?
+                    .to_vec();
+                let mut strings = Vec::with_capacity(moves.len());
+                for mov in moves {
+                    strings.push(
+                        game.get_move_str(player, mov)
+                            .map_err(|e| describe(&e))?
+                            .to_string(),
+                    );
+                }
+                println!("{}", strings.join(" "));
+            }
+            string => {
+                let mov = game
+                    .get_move_code(player, string)
+                    .and_then(|mov| game.is_legal_move(player, mov).map(|()| mov));
+                match mov {
+                    Ok(mov) => return Ok(Some(mov)),
+                    Err(error) => println!("illegal move: {}", describe(&error)),
+                }
+            }
+        }
+    }
+}
+
+fn search(game: &mut Game, budget: Budget, seed: u64) -> Result<move_code, String> {
+    let config = mcts::Config {
+        budget,
+        seed,
+        ..Default::default()
+    };
+    let mut mcts = Mcts::new(game.sizer(), config);
+    let mut state = Hosted(game.try_clone().map_err(|e| describe(&e))?);
+    let analysis = mcts.search(&mut state).map_err(|e| describe(&e))?;
+    analysis
+        .best
+        .map(|(_, mov)| mov)
+        .ok_or_else(|| "MCTS found no move".to_string())
+}
+
+fn results(winners: &[player_id]) -> String {
+    match winners {
+        [] => "game over: draw".to_string(),
+        [winner] => format!("game over: player {winner} wins"),
+        _ => {
+            let winners: Vec<_> = winners.iter().map(player_id::to_string).collect();
+            format!("game over: players {} win", winners.join(", "))
+        }
+    }
+}
+
+/// Returns `<GAME>.<VARIANT>.<IMPL>`.
+fn name(methods: &game_methods) -> String {
+    let part = |string: *const c_char| {
+        // The names of game methods are valid C strings.
+        unsafe { CStr::from_ptr(string) }.to_string_lossy()
+    };
+    format!(
+        "{}.{}.{}",
+        part(methods.game_name),
+        part(methods.variant_name),
+        part(methods.impl_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_args() {
+        let parsed = args(&[
+            "--plugin",
+            "a.so",
+            "--plugin",
+            "b.so",
+            "--game",
+            "Nim",
+            "--options",
+            "21 3",
+            "--state",
+            "A 5",
+            "--player",
+            "2=random",
+            "--seed",
+            "7",
+            "--list",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(vec!["a.so", "b.so"], parsed.plugins);
+        assert_eq!(Some("Nim"), parsed.game.as_deref());
+        assert_eq!(Some("21 3"), parsed.options.as_deref());
+        assert_eq!(Some("A 5"), parsed.state.as_deref());
+        assert_eq!(HashMap::from([(2, Kind::Random)]), parsed.players);
+        assert_eq!(7, parsed.seed);
+        assert!(parsed.list);
+
+        assert!(args(&["--list", "--help"]).unwrap().is_none());
+        assert!(args(&["--game"]).is_err());
+        assert!(args(&["--seed", "x"]).is_err());
+        assert!(args(&["--player", "1"]).is_err());
+        assert!(args(&["--unknown"]).is_err());
+    }
+
+    #[test]
+    fn parses_players() {
+        assert_eq!(Ok((1, Kind::Human)), parse_player("1=human"));
+        assert_eq!(Ok((2, Kind::Random)), parse_player("2=random"));
+        assert_eq!(
+            Ok((1, Kind::Mcts(Budget::default()))),
+            parse_player("1=mcts")
+        );
+        let budget = Budget {
+            iterations: Some(100),
+            time: None,
+        };
+        assert_eq!(Ok((3, Kind::Mcts(budget))), parse_player("3=mcts:100"));
+
+        for spec in [
+            "1",
+            "0=human",
+            "255=human",
+            "x=human",
+            "1=alien",
+            "1=mcts:x",
+        ] {
+            assert!(parse_player(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn selects_games() {
+        let nim = example::example_game_methods();
+        let available = [&nim];
+        assert!(std::ptr::eq(&nim, select(&available, None).unwrap()));
+        for query in ["Nim", "Nim.Standard", "Nim.Standard.surena_game_rs"] {
+            assert!(select(&available, Some(query)).is_ok(), "{query}");
+        }
+        assert!(select(&available, Some("Ni")).is_err());
+        assert!(select(&available, Some("Chess")).is_err());
+
+        let other = example::example_game_methods();
+        let available = [&nim, &other];
+        assert!(select(&available, None).is_err());
+        assert!(select(&available, Some("Nim")).is_err());
+    }
+
+    #[test]
+    fn searches_hosted_games() {
+        let nim = example::example_game_methods();
+        let init_info = GameInit::Standard {
+            opts: Some("6 3"),
+            legacy: None,
+            state: None,
+        };
+        let mut game = Game::new(&nim, &init_info).unwrap();
+        let budget = Budget {
+            iterations: Some(1000),
+            time: None,
+        };
+        // Leaving 4n + 1 counters wins.
+        assert_eq!(Ok(1), search(&mut game, budget, 0));
+        assert_eq!("A 6", game.export_state().unwrap());
+    }
+}
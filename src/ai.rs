@@ -0,0 +1,310 @@
+//! Game-independent AI players for any [`State`], e.g., any [`GameMethods`]
+//! implementation.
+//!
+//! The searches use the required methods of the trait and clone states via
+//! [`State::try_clone`].
+//! Optional methods are only used if the caller enables their features.
+
+pub mod alpha_beta;
+pub mod mcts;
+
+use crate::{buf_sizer, move_code, player_id, playout, sync_counter, GameMethods, PtrVec, Result};
+
+use std::time::{Duration, Instant};
+
+/// A game state which can be searched.
+///
+/// This is implemented for every [`GameMethods`] implementation.
+/// Other games, e.g., those driven via [`host::Game`](crate::host::Game), can
+/// implement it directly, as it neither requires [`Send`] nor infallible
+/// cloning.
+///
+/// The methods are named differently from their [`GameMethods`]
+/// counterparts, so calls stay unambiguous with both traits in scope:
+/// ```
+/// use surena_game::{ai::State, *};
+///
+/// fn step<G: GameMethods>(game: &mut G, mov: move_code) -> Result<()> {
+///     let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+///     game.make_move(1, mov, &mut sync_ctr)?;
+///     game.play(1, mov, &mut sync_ctr)
+/// }
+/// ```
+pub trait State: Sized {
+    /// Clones the state, which can fail, e.g., for hosted games.
+    fn try_clone(&mut self) -> Result<Self>;
+    /// See [`GameMethods::copy_from`].
+    fn copy_state(&mut self, other: &mut Self) -> Result<()>;
+    /// See [`GameMethods::players_to_move`].
+    fn movers(&mut self, players: &mut PtrVec<player_id>) -> Result<()>;
+    /// See [`GameMethods::get_concrete_moves`].
+    fn moves(&mut self, player: player_id, moves: &mut PtrVec<move_code>) -> Result<()>;
+    /// See [`GameMethods::make_move`].
+    fn play(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()>;
+    /// See [`GameMethods::get_results`].
+    fn winners(&mut self, players: &mut PtrVec<player_id>) -> Result<()>;
+
+    /// See [`GameMethods::get_random_move`].
+    ///
+    /// Must be implemented when searching games with random moves.
+    #[allow(unused_variables)]
+    fn random_move(&mut self, seed: u64) -> Result<move_code> {
+        unimplemented!("random_move")
+    }
+    /// See [`GameMethods::get_move_ordering`].
+    ///
+    /// Must be implemented when the searches are configured to use move
+    /// ordering.
+    #[allow(unused_variables)]
+    fn ordered_moves(&mut self, player: player_id, moves: &mut PtrVec<move_code>) -> Result<()> {
+        unimplemented!("ordered_moves")
+    }
+    /// See [`GameMethods::get_id`].
+    ///
+    /// Must be implemented when the searches are configured to use ids.
+    fn id(&mut self) -> Result<u64> {
+        unimplemented!("id")
+    }
+    /// See [`GameMethods::get_eval`].
+    ///
+    /// Must be implemented when the searches are configured to use
+    /// evaluations.
+    #[allow(unused_variables)]
+    fn eval(&mut self, player: player_id) -> Result<f32> {
+        unimplemented!("eval")
+    }
+    /// See [`GameMethods::playout`].
+    ///
+    /// The default implementation uses [`playout::random_playout`].
+    fn rollout(
+        &mut self,
+        seed: u64,
+        sizer: &buf_sizer,
+        max_moves: usize,
+        sync_ctr: &mut sync_counter,
+        winners: Option<&mut PtrVec<player_id>>,
+    ) -> Result<bool> {
+        playout::random_playout(self, seed, sizer, max_moves, sync_ctr, winners)
+    }
+}
+
+impl<G: GameMethods> State for G {
+    #[inline]
+    fn try_clone(&mut self) -> Result<Self> {
+        Ok(self.clone())
+    }
+
+    #[inline]
+    fn copy_state(&mut self, other: &mut Self) -> Result<()> {
+        self.copy_from(other)
+    }
+
+    #[inline]
+    fn movers(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        self.players_to_move(players)
+    }
+
+    #[inline]
+    fn moves(&mut self, player: player_id, moves: &mut PtrVec<move_code>) -> Result<()> {
+        self.get_concrete_moves(player, moves)
+    }
+
+    #[inline]
+    fn play(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
+        self.make_move(player, mov, sync_ctr)
+    }
+
+    #[inline]
+    fn winners(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        self.get_results(players)
+    }
+
+    #[inline]
+    fn random_move(&mut self, seed: u64) -> Result<move_code> {
+        self.get_random_move(seed)
+    }
+
+    #[inline]
+    fn ordered_moves(&mut self, player: player_id, moves: &mut PtrVec<move_code>) -> Result<()> {
+        self.get_move_ordering(player, moves)
+    }
+
+    #[inline]
+    fn id(&mut self) -> Result<u64> {
+        self.get_id()
+    }
+
+    #[inline]
+    fn eval(&mut self, player: player_id) -> Result<f32> {
+        self.get_eval(player)
+    }
+
+    #[inline]
+    fn rollout(
+        &mut self,
+        seed: u64,
+        sizer: &buf_sizer,
+        max_moves: usize,
+        sync_ctr: &mut sync_counter,
+        winners: Option<&mut PtrVec<player_id>>,
+    ) -> Result<bool> {
+        self.playout(seed, sizer, max_moves, sync_ctr, winners)
+    }
+}
+
+/// Limits for the effort spent on a search.
+///
+/// The search stops as soon as one of the limits is reached.
+/// Without any limits, a search might not terminate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    pub iterations: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            iterations: Some(10_000),
+            time: None,
+        }
+    }
+}
+
+impl Budget {
+    /// Starts measuring the time for this budget.
+    pub(crate) fn start(self) -> Timer {
+        Timer {
+            budget: self,
+            start: Instant::now(),
+        }
+    }
+}
+
+pub(crate) struct Timer {
+    budget: Budget,
+    start: Instant,
+}
+
+impl Timer {
+    /// Returns whether the budget is used up after `iterations`.
+    pub(crate) fn exhausted(&self, iterations: u64) -> bool {
+        self.budget.iterations.is_some_and(|max| iterations >= max)
+            || self
+                .budget
+                .time
+                .is_some_and(|max| self.start.elapsed() >= max)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Two players alternately take one or two tokens and whoever takes the
+    /// last one wins.
+    ///
+    /// Multiples of three are lost for the player to move.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub(crate) struct Take {
+        pub(crate) tokens: u8,
+        pub(crate) turn: player_id,
+    }
+
+    impl Take {
+        pub(crate) fn new(tokens: u8) -> Self {
+            Self { tokens, turn: 1 }
+        }
+
+        pub(crate) fn sizer() -> buf_sizer {
+            buf_sizer {
+                player_count: 2,
+                max_players_to_move: 1,
+                max_moves: 2,
+                max_results: 1,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl State for Take {
+        fn try_clone(&mut self) -> Result<Self> {
+            Ok(self.clone())
+        }
+
+        fn copy_state(&mut self, other: &mut Self) -> Result<()> {
+            self.clone_from(other);
+            Ok(())
+        }
+
+        fn movers(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+            if self.tokens > 0 {
+                players.push(self.turn);
+            }
+            Ok(())
+        }
+
+        fn moves(&mut self, _player: player_id, moves: &mut PtrVec<move_code>) -> Result<()> {
+            moves.extend(1..=self.tokens.min(2).into());
+            Ok(())
+        }
+
+        fn play(
+            &mut self,
+            _player: player_id,
+            mov: move_code,
+            _sync_ctr: &mut sync_counter,
+        ) -> Result<()> {
+            self.tokens -= mov as u8;
+            self.turn = 3 - self.turn;
+            Ok(())
+        }
+
+        fn winners(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+            if self.tokens == 0 {
+                players.push(3 - self.turn);
+            }
+            Ok(())
+        }
+
+        fn id(&mut self) -> Result<u64> {
+            Ok((u64::from(self.tokens) << 8) | u64::from(self.turn))
+        }
+    }
+
+    #[test]
+    fn budget_limits_iterations() {
+        let timer = Budget {
+            iterations: Some(3),
+            time: None,
+        }
+        .start();
+        assert!(!timer.exhausted(2));
+        assert!(timer.exhausted(3));
+    }
+
+    #[test]
+    fn budget_limits_time() {
+        let timer = Budget {
+            iterations: None,
+            time: Some(Duration::ZERO),
+        }
+        .start();
+        assert!(timer.exhausted(0));
+        let unlimited = Budget {
+            iterations: None,
+            time: None,
+        };
+        assert!(!unlimited.start().exhausted(u64::MAX));
+    }
+}
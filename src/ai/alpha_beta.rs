@@ -0,0 +1,461 @@
+//! Iterative-deepening alpha-beta search for two-player games.
+//!
+//! Scores are given from the perspective of the player to move in the
+//! searched state.
+//! Wins and losses are scored as ±[`WIN`] reduced by the number of moves
+//! until the end of the game, draws as `0`.
+//! Hence, faster wins and slower losses are preferred.
+//!
+//! At the depth limit, states are evaluated via [`State::eval`] if the
+//! [`game_feature_flags::eval`] is enabled and as `0` otherwise.
+//! With the [`game_feature_flags::id`], [`State::id`] is used as key for a
+//! transposition table.
+//! With the [`game_feature_flags::move_ordering`], moves are searched in the
+//! order of [`State::ordered_moves`].
+//!
+//! # Example
+//! ```ignore
+//! let (mut game, sizer) = MyGame::create(&GameInit::Default)?;
+//! let mut search = AlphaBeta::new(&sizer, features, Config::default());
+//! let analysis = search.search(&mut game)?;
+//! if analysis.solved && analysis.score > 0.0 {
+//!     println!("the player to move wins with {:?}", analysis.pv);
+//! }
+//! ```
+
+use super::{Budget, State, Timer};
+use crate::{
+    buf_sizer, game_feature_flags, move_code, player_id, ptr_vec::Storage, Error, ErrorCode,
+    Result, PLAYER_RAND, SYNC_COUNTER_DEFAULT,
+};
+
+use std::collections::HashMap;
+
+/// Score of a won game.
+pub const WIN: f32 = 1_000_000.0;
+/// Scores beyond this magnitude are wins or losses.
+const WIN_THRESHOLD: f32 = WIN / 2.0;
+
+/// Configuration of an [`AlphaBeta`] search.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// [`Budget::iterations`] limits the number of visited states.
+    ///
+    /// The first iteration with depth `1` is always completed.
+    pub budget: Budget,
+    /// Maximum depth in moves, at least `1`.
+    pub max_depth: u32,
+    /// Maximum number of transposition table entries.
+    pub tt_entries: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            budget: Budget {
+                iterations: None,
+                time: None,
+            },
+            max_depth: 64,
+            tt_entries: 1 << 20,
+        }
+    }
+}
+
+/// Result of [`AlphaBeta::search`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// Score of the state for the player to move.
+    pub score: f32,
+    /// Principal variation, i.e., the expected moves of both players.
+    pub pv: Vec<(player_id, move_code)>,
+    /// Depth of the last completed iteration.
+    pub depth: u32,
+    /// Number of visited states.
+    pub nodes: u64,
+    /// Whether [`Self::score`] is exact because the search reached the end
+    /// of the game in all lines.
+    pub solved: bool,
+}
+
+impl Analysis {
+    /// The first move of the principal variation.
+    pub fn best(&self) -> Option<(player_id, move_code)> {
+        self.pv.first().copied()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    depth: u32,
+    value: f32,
+    bound: Bound,
+    /// Whether the depth limit was reached below this state.
+    limited: bool,
+    best: Option<move_code>,
+}
+
+/// Reusable alpha-beta searcher for a game with a specific [`buf_sizer`].
+pub struct AlphaBeta {
+    config: Config,
+    features: game_feature_flags,
+    players: Storage<player_id>,
+    moves: Storage<move_code>,
+    winners: Storage<player_id>,
+    tt: HashMap<u64, Entry>,
+    pv: Vec<Vec<(player_id, move_code)>>,
+    nodes: u64,
+    limited: bool,
+}
+
+impl AlphaBeta {
+    /// `sizer` must be the [`buf_sizer`] and `features` the
+    /// [`game_feature_flags`] of the games which are searched.
+    pub fn new(sizer: &buf_sizer, features: game_feature_flags, config: Config) -> Self {
+        Self {
+            config,
+            features,
+            players: Storage::new(sizer.max_players_to_move.into()),
+            moves: Storage::new(sizer.max_moves as usize),
+            winners: Storage::new(sizer.max_results.into()),
+            tt: HashMap::new(),
+            pv: vec![],
+            nodes: 0,
+            limited: false,
+        }
+    }
+
+    /// Searches the current state of `game` with increasing depth until it
+    /// is solved or the [`Config::max_depth`] or [`Config::budget`] is
+    /// reached.
+    ///
+    /// `game` is not modified.
+    /// Returns an error for random or simultaneous moves.
+    pub fn search<G: State>(&mut self, game: &mut G) -> Result<Analysis> {
+        let timer = self.config.budget.start();
+        self.tt.clear();
+        self.nodes = 0;
+        let root = match self.mover(game)? {
+            Some(player) => player,
+            None => {
+                return Ok(Analysis {
+                    score: self.terminal(game, PLAYER_RAND, 0)?,
+                    pv: vec![],
+                    depth: 0,
+                    nodes: 1,
+                    solved: true,
+                })
+            }
+        };
+
+        let mut analysis = None;
+        for depth in 1..=self.config.max_depth.max(1) {
+            self.limited = false;
+            let mut state = game.try_clone()?;
+            // The first iteration is always completed to find a move.
+            let limit = (depth > 1).then_some(&timer);
+            let window = (-f32::INFINITY, f32::INFINITY);
+            let score = match self.minimax(&mut state, root, depth, window, 0, limit)? {
+                Some(score) => score,
+                // Keep the result of the last completed iteration.
+                None => break,
+            };
+            analysis = Some(Analysis {
+                score,
+                pv: self.pv[0].clone(),
+                depth,
+                nodes: self.nodes,
+                solved: !self.limited,
+            });
+            if !self.limited || timer.exhausted(self.nodes) {
+                break;
+            }
+        }
+
+        let mut analysis = analysis.expect("at least one iteration is started");
+        analysis.nodes = self.nodes;
+        Ok(analysis)
+    }
+
+    /// Returns the score of `state` for `root` within the window
+    /// `(alpha, beta)` or [`None`] if the budget is exhausted.
+    ///
+    /// All players except `root` are treated as its opponent.
+    fn minimax<G: State>(
+        &mut self,
+        state: &mut G,
+        root: player_id,
+        depth: u32,
+        (mut alpha, mut beta): (f32, f32),
+        ply: usize,
+        timer: Option<&Timer>,
+    ) -> Result<Option<f32>> {
+        self.nodes += 1;
+        if self.pv.len() <= ply {
+            self.pv.resize(ply + 1, vec![]);
+        }
+        self.pv[ply].clear();
+        if timer.is_some_and(|t| t.exhausted(self.nodes)) {
+            return Ok(None);
+        }
+
+        let mover = match self.mover(state)? {
+            Some(player) => player,
+            None => return self.terminal(state, root, ply).map(Some),
+        };
+        if depth == 0 {
+            self.limited = true;
+            return self.eval(state, root).map(Some);
+        }
+
+        let id = if self.features.id() {
+            Some(state.id()?)
+        } else {
+            None
+        };
+        let mut tt_move = None;
+        if let Some(entry) = id.and_then(|id| self.tt.get(&id)) {
+            tt_move = entry.best;
+            if entry.depth >= depth {
+                let value = from_tt(entry.value, ply);
+                match entry.bound {
+                    Bound::Exact => {
+                        self.limited |= entry.limited;
+                        self.pv[ply].extend(entry.best.map(|m| (mover, m)));
+                        return Ok(Some(value));
+                    }
+                    Bound::Lower => alpha = alpha.max(value),
+                    Bound::Upper => beta = beta.min(value),
+                }
+                if alpha >= beta {
+                    self.limited |= entry.limited;
+                    return Ok(Some(value));
+                }
+            }
+        }
+
+        let mut moves = self.moves(state, mover)?;
+        if let Some(pos) = tt_move.and_then(|m| moves.iter().position(|&n| n == m)) {
+            moves[..=pos].rotate_right(1);
+        }
+
+        let maximize = mover == root;
+        let (original_alpha, original_beta) = (alpha, beta);
+        let outer_limited = std::mem::replace(&mut self.limited, false);
+        let mut best_value = if maximize {
+            -f32::INFINITY
+        } else {
+            f32::INFINITY
+        };
+        let mut best_move = None;
+        for mov in moves {
+            let mut child = state.try_clone()?;
+            let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+            child.play(mover, mov, &mut sync_ctr)?;
+            let window = (alpha, beta);
+            let value = match self.minimax(&mut child, root, depth - 1, window, ply + 1, timer)? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+
+            let better = if maximize {
+                value > best_value
+            } else {
+                value < best_value
+            };
+            if better {
+                best_value = value;
+                best_move = Some(mov);
+                let (head, tail) = self.pv.split_at_mut(ply + 1);
+                head[ply].clear();
+                head[ply].push((mover, mov));
+                head[ply].extend_from_slice(&tail[0]);
+            }
+            if maximize {
+                alpha = alpha.max(value);
+            } else {
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let limited = self.limited;
+        self.limited |= outer_limited;
+        if let Some(id) = id {
+            if self.tt.len() < self.config.tt_entries || self.tt.contains_key(&id) {
+                let bound = if best_value <= original_alpha {
+                    Bound::Upper
+                } else if best_value >= original_beta {
+                    Bound::Lower
+                } else {
+                    Bound::Exact
+                };
+                let entry = Entry {
+                    depth,
+                    value: to_tt(best_value, ply),
+                    bound,
+                    limited,
+                    best: best_move,
+                };
+                self.tt.insert(id, entry);
+            }
+        }
+        Ok(Some(best_value))
+    }
+
+    /// Returns the only player to move or [`None`] if the game is over.
+    fn mover<G: State>(&mut self, state: &mut G) -> Result<Option<player_id>> {
+        state.movers(&mut self.players.get_ptr_vec())?;
+        match *self.players {
+            [] => Ok(None),
+            [PLAYER_RAND] => Err(Error::new_static(
+                ErrorCode::FeatureUnsupported,
+                "alpha-beta search does not support random moves\0",
+            )),
+            [player] => Ok(Some(player)),
+            _ => Err(Error::new_static(
+                ErrorCode::FeatureUnsupported,
+                "alpha-beta search does not support simultaneous moves\0",
+            )),
+        }
+    }
+
+    fn moves<G: State>(&mut self, state: &mut G, player: player_id) -> Result<Vec<move_code>> {
+        let mut moves = self.moves.get_ptr_vec();
+        if self.features.move_ordering() {
+            state.ordered_moves(player, &mut moves)?;
+        } else {
+            state.moves(player, &mut moves)?;
+        }
+        Ok(self.moves.to_vec())
+    }
+
+    /// Scores a finished game for `root`.
+    fn terminal<G: State>(&mut self, state: &mut G, root: player_id, ply: usize) -> Result<f32> {
+        state.winners(&mut self.winners.get_ptr_vec())?;
+        let win = WIN - ply as f32;
+        Ok(if self.winners.is_empty() {
+            0.0
+        } else if self.winners.contains(&root) {
+            win
+        } else {
+            -win
+        })
+    }
+
+    fn eval<G: State>(&mut self, state: &mut G, root: player_id) -> Result<f32> {
+        if !self.features.eval() {
+            return Ok(0.0);
+        }
+        let value = state.eval(root)?;
+        Ok(value.clamp(-WIN_THRESHOLD, WIN_THRESHOLD))
+    }
+}
+
+/// Converts win scores relative to the root into scores relative to the
+/// state at `ply`.
+fn to_tt(value: f32, ply: usize) -> f32 {
+    if value > WIN_THRESHOLD {
+        value + ply as f32
+    } else if value < -WIN_THRESHOLD {
+        value - ply as f32
+    } else {
+        value
+    }
+}
+
+/// Inverse of [`to_tt`].
+fn from_tt(value: f32, ply: usize) -> f32 {
+    if value > WIN_THRESHOLD {
+        value - ply as f32
+    } else if value < -WIN_THRESHOLD {
+        value + ply as f32
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::tests::Take;
+
+    fn search(game: &mut Take, id: bool, config: Config) -> Analysis {
+        let mut features = game_feature_flags::default();
+        features.set_id(id);
+        AlphaBeta::new(&Take::sizer(), features, config)
+            .search(game)
+            .unwrap()
+    }
+
+    #[test]
+    fn solves_won_state() {
+        let mut game = Take::new(4);
+        let analysis = search(&mut game, false, Config::default());
+        assert!(analysis.solved);
+        // Leaving three tokens wins after three moves.
+        assert_eq!(WIN - 3.0, analysis.score);
+        assert_eq!(Some((1, 1)), analysis.best());
+        assert_eq!(3, analysis.pv.len());
+        assert_eq!(Take::new(4), game);
+    }
+
+    #[test]
+    fn solves_lost_state() {
+        let analysis = search(&mut Take::new(3), false, Config::default());
+        assert!(analysis.solved);
+        assert_eq!(-(WIN - 2.0), analysis.score);
+    }
+
+    #[test]
+    fn transpositions_keep_scores() {
+        for tokens in 1..=20 {
+            let without = search(&mut Take::new(tokens), false, Config::default());
+            let with = search(&mut Take::new(tokens), true, Config::default());
+            assert!(with.solved, "{tokens} tokens");
+            assert_eq!(without.score, with.score, "{tokens} tokens");
+            assert_eq!(tokens % 3 == 0, with.score < 0.0, "{tokens} tokens");
+        }
+    }
+
+    #[test]
+    fn depth_limit_is_not_solved() {
+        let config = Config {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let analysis = search(&mut Take::new(30), false, config);
+        assert!(!analysis.solved);
+        assert_eq!(1, analysis.depth);
+        assert_eq!(0.0, analysis.score);
+        assert!(analysis.best().is_some());
+    }
+
+    #[test]
+    fn finished_games_are_solved() {
+        let analysis = search(&mut Take::new(0), false, Config::default());
+        assert!(analysis.solved);
+        assert_eq!(None, analysis.best());
+        // The player to move lost by the last move.
+        assert_eq!(-WIN, analysis.score);
+    }
+
+    #[test]
+    fn tt_scores_are_relative_to_the_state() {
+        assert_eq!(WIN - 2.0, to_tt(WIN - 5.0, 3));
+        assert_eq!(-(WIN - 2.0), to_tt(-(WIN - 5.0), 3));
+        assert_eq!(42.0, to_tt(42.0, 3));
+        for value in [WIN - 5.0, -(WIN - 5.0), 42.0, -42.0, 0.0] {
+            assert_eq!(value, from_tt(to_tt(value, 3), 3));
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Monte-Carlo tree search (MCTS) using UCT.
+//!
+//! Playouts are performed via [`State::rollout`], which plays random moves by
+//! default.
+//! Every player receives the reward `1 / n` if it is one of `n` winners and
+//! `0` otherwise.
+//! A draw without winners rewards all players equally.
+//!
+//! Moves of [`PLAYER_RAND`] are drawn via [`State::random_move`]
+//! and are not part of the tree (open-loop search).
+//!
+//! # Example
+//! ```ignore
+//! let (mut game, sizer) = MyGame::create(&GameInit::Default)?;
+//! let mut mcts = Mcts::new(&sizer, Config::default());
+//! let analysis = mcts.search(&mut game)?;
+//! if let Some((player, mov)) = analysis.best {
+//!     game.make_move(player, mov, &mut sync_ctr)?;
+//! }
+//! ```
+
+use super::{Budget, State};
+use crate::{
+    buf_sizer, move_code, player_id, playout, ptr_vec::Storage, rng::Rng, Error, ErrorCode, Result,
+    PLAYER_RAND, SYNC_COUNTER_DEFAULT,
+};
+
+/// Configuration of an [`Mcts`].
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub budget: Budget,
+    /// Exploration constant of the UCT formula.
+    pub exploration: f64,
+    /// Seed for move selection and playouts.
+    ///
+    /// The random number generator is reseeded at the start of every
+    /// search, so searches with the same seed and an iteration budget are
+    /// deterministic.
+    pub seed: u64,
+    /// Maximum number of moves per playout.
+    ///
+    /// Longer playouts are stopped and count as a draw.
+    /// This keeps iterations short, as the [`Config::budget`] is only
+    /// checked between them.
+    pub max_playout_moves: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            budget: Budget::default(),
+            exploration: std::f64::consts::SQRT_2,
+            seed: 0,
+            max_playout_moves: playout::MAX_MOVES,
+        }
+    }
+}
+
+/// Result of [`Mcts::search`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// The most visited move and the player making it.
+    ///
+    /// This is [`None`] if the game is already over or no iteration was
+    /// performed.
+    pub best: Option<(player_id, move_code)>,
+    /// Average reward of [`Self::best`] for its player between `0` and `1`.
+    pub value: f64,
+    /// Number of performed iterations.
+    pub iterations: u64,
+}
+
+/// Reusable MCTS instance for a game with a specific [`buf_sizer`].
+pub struct Mcts {
+    config: Config,
+    rng: Rng,
+    player_count: usize,
+    nodes: Vec<Node>,
+    players: Storage<player_id>,
+    moves: Storage<move_code>,
+    winners: Storage<player_id>,
+    sizer: buf_sizer,
+}
+
+struct Node {
+    /// Player who made the move leading to this node.
+    player: player_id,
+    mov: move_code,
+    visits: u32,
+    /// Sum of the rewards of `player`.
+    reward: f64,
+    children: Vec<usize>,
+}
+
+impl Node {
+    fn new(player: player_id, mov: move_code) -> Self {
+        Self {
+            player,
+            mov,
+            visits: 0,
+            reward: 0.0,
+            children: vec![],
+        }
+    }
+}
+
+impl Mcts {
+    /// `sizer` must be the [`buf_sizer`] of the games which are searched.
+    pub fn new(sizer: &buf_sizer, config: Config) -> Self {
+        Self {
+            rng: Rng::new(config.seed),
+            config,
+            player_count: sizer.player_count.into(),
+            nodes: vec![],
+            players: Storage::new(sizer.max_players_to_move.into()),
+            moves: Storage::new(sizer.max_moves as usize),
+            winners: Storage::new(sizer.max_results.into()),
+            sizer: *sizer,
+        }
+    }
+
+    /// Searches the best move in the current state of `game`.
+    ///
+    /// `game` is not modified.
+    /// Returns an error if [`PLAYER_RAND`] is to move, as random moves must
+    /// be made by the caller.
+    pub fn search<G: State>(&mut self, game: &mut G) -> Result<Analysis> {
+        let timer = self.config.budget.start();
+        self.rng = Rng::new(self.config.seed);
+        self.nodes.clear();
+        self.nodes.push(Node::new(PLAYER_RAND, 0));
+
+        let root_actions = self.actions(game)?;
+        if root_actions.iter().any(|&(p, _)| p == PLAYER_RAND) {
+            return Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "cannot search while PLAYER_RAND is to move\0",
+            ));
+        }
+        if root_actions.is_empty() {
+            return Ok(Analysis {
+                best: None,
+                value: 0.0,
+                iterations: 0,
+            });
+        }
+
+        let mut state = game.try_clone()?;
+        let mut iterations = 0;
+        let mut path = vec![];
+        let mut rewards = vec![0.0; self.player_count];
+        while !timer.exhausted(iterations) {
+            state.copy_state(game)?;
+            path.clear();
+            path.push(0);
+            self.descend(&mut state, &mut path)?;
+            self.rollout(&mut state, &mut rewards)?;
+            for &index in &path {
+                let node = &mut self.nodes[index];
+                node.visits += 1;
+                node.reward += reward_of(&rewards, node.player);
+            }
+            iterations += 1;
+        }
+
+        let best = self.nodes[0]
+            .children
+            .iter()
+            .map(|&c| &self.nodes[c])
+            .max_by_key(|n| n.visits);
+        Ok(Analysis {
+            best: best.map(|n| (n.player, n.mov)),
+            value: best.map_or(0.0, |n| n.reward / f64::from(n.visits.max(1))),
+            iterations,
+        })
+    }
+
+    /// Follows the tree from the root and expands one new node.
+    fn descend<G: State>(&mut self, state: &mut G, path: &mut Vec<usize>) -> Result<()> {
+        let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+        let mut node = 0;
+        loop {
+            let actions = self.actions(state)?;
+            if let Some(&(player, _)) = actions.iter().find(|&&(p, _)| p == PLAYER_RAND) {
+                let mov = state.random_move(self.rng.next_u64())?;
+                state.play(player, mov, &mut sync_ctr)?;
+                continue;
+            }
+            if actions.is_empty() {
+                return Ok(());
+            }
+
+            let untried: Vec<_> = actions
+                .iter()
+                .filter(|&&action| self.child(node, action).is_none())
+                .collect();
+            if !untried.is_empty() {
+                let &(player, mov) = untried[self.rng.index(untried.len())];
+                let child = self.nodes.len();
+                self.nodes.push(Node::new(player, mov));
+                self.nodes[node].children.push(child);
+                path.push(child);
+                return state.play(player, mov, &mut sync_ctr);
+            }
+
+            node = self.select(node, &actions);
+            path.push(node);
+            let child = &self.nodes[node];
+            state.play(child.player, child.mov, &mut sync_ctr)?;
+        }
+    }
+
+    /// Returns the child of `node` for `action` if it has been expanded.
+    fn child(&self, node: usize, (player, mov): (player_id, move_code)) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].player == player && self.nodes[c].mov == mov)
+    }
+
+    /// Selects the child of `node` with the highest UCT value among the
+    /// `actions`, which must all be expanded.
+    fn select(&self, node: usize, actions: &[(player_id, move_code)]) -> usize {
+        let log_visits = f64::from(self.nodes[node].visits.max(1)).ln();
+        actions
+            .iter()
+            .filter_map(|&action| self.child(node, action))
+            .map(|c| {
+                let child = &self.nodes[c];
+                let visits = f64::from(child.visits.max(1));
+                let uct =
+                    child.reward / visits + self.config.exploration * (log_visits / visits).sqrt();
+                (c, uct)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(c, _)| c)
+            .expect("all actions are expanded")
+    }
+
+    /// Plays the game to the end or at most [`Config::max_playout_moves`]
+    /// and writes the reward of each player.
+    fn rollout<G: State>(&mut self, state: &mut G, rewards: &mut [f64]) -> Result<()> {
+        let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+        let mut winners = self.winners.get_ptr_vec();
+        // Unfinished playouts write no winners and count as a draw.
+        state.rollout(
+            self.rng.next_u64(),
+            &self.sizer,
+            self.config.max_playout_moves,
+            &mut sync_ctr,
+            Some(&mut winners),
+        )?;
+
+        rewards.fill(0.0);
+        if self.winners.is_empty() {
+            rewards.fill(1.0 / rewards.len().max(1) as f64);
+        } else {
+            let share = 1.0 / self.winners.len() as f64;
+            for &winner in self.winners.iter() {
+                if let Some(reward) = rewards.get_mut(usize::from(winner).wrapping_sub(1)) {
+                    *reward += share;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns all moves of all players to move.
+    fn actions<G: State>(&mut self, state: &mut G) -> Result<Vec<(player_id, move_code)>> {
+        state.movers(&mut self.players.get_ptr_vec())?;
+        let mut actions = vec![];
+        for &player in self.players.iter() {
+            if player == PLAYER_RAND {
+                actions.push((player, 0));
+                continue;
+            }
+            state.moves(player, &mut self.moves.get_ptr_vec())?;
+            actions.extend(self.moves.iter().map(|&m| (player, m)));
+        }
+        Ok(actions)
+    }
+}
+
+fn reward_of(rewards: &[f64], player: player_id) -> f64 {
+    rewards
+        .get(usize::from(player).wrapping_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::tests::Take;
+
+    fn config(iterations: u64) -> Config {
+        Config {
+            budget: Budget {
+                iterations: Some(iterations),
+                time: None,
+            },
+            seed: 42,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_winning_move() {
+        let mut game = Take::new(4);
+        let analysis = Mcts::new(&Take::sizer(), config(2000))
+            .search(&mut game)
+            .unwrap();
+        // Leaving three tokens wins.
+        assert_eq!(Some((1, 1)), analysis.best);
+        assert!(analysis.value > 0.5);
+        assert_eq!(2000, analysis.iterations);
+        assert_eq!(Take::new(4), game);
+    }
+
+    #[test]
+    fn is_deterministic() {
+        let mut game = Take::new(20);
+        let mut search = Mcts::new(&Take::sizer(), config(500));
+        let first = search.search(&mut game).unwrap();
+        assert_eq!(first, search.search(&mut game).unwrap());
+        let mut other = Mcts::new(&Take::sizer(), config(500));
+        assert_eq!(first, other.search(&mut game).unwrap());
+    }
+
+    #[test]
+    fn scores_long_playouts_as_draws() {
+        let limited = Config {
+            max_playout_moves: 0,
+            ..config(10)
+        };
+        // The tree does not reach the end of the game within 10 iterations.
+        let analysis = Mcts::new(&Take::sizer(), limited)
+            .search(&mut Take::new(30))
+            .unwrap();
+        assert_eq!(0.5, analysis.value);
+    }
+
+    #[test]
+    fn finished_games_have_no_best_move() {
+        let analysis = Mcts::new(&Take::sizer(), config(10))
+            .search(&mut Take::new(0))
+            .unwrap();
+        assert_eq!(None, analysis.best);
+        assert_eq!(0, analysis.iterations);
+    }
+}
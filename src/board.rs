@@ -0,0 +1,426 @@
+//! Fixed-size grid boards, coordinates, and notation.
+//!
+//! A [`Grid`] stores one cell per [`Coord`] of a `W`×`H` board in row-major
+//! order.
+//! Neighbors depend on the [`Topology`], which is [`Square4`], [`Square8`], or
+//! [`Hex`].
+//!
+//! Coordinates are written in algebraic notation, where the file is a letter
+//! and the rank a 1-based number, e.g., `"e4"` for `Coord { x: 4, y: 3 }`.
+//! Moves between two coordinates are written as `"c3-d5"`.
+//! Hence, at most 26 files are supported.
+//!
+//! # Example
+//! ```
+//! use surena_game::{board::*, ptr_vec::Storage};
+//!
+//! type Board = Grid<char, 3, 3>;
+//!
+//! let mut board = Board::filled('.');
+//! let mov = Board::coord_move_code("b2")?;
+//! board[Board::decode(mov)?] = 'X';
+//! assert_eq!(4, board.neighbors::<Square4>(Coord::new(1, 1)).count());
+//!
+//! let mut storage = Storage::new(Board::print_str_size::<Square4>());
+//! board.print::<Square4>(&mut storage.get_ptr_vec(), |c| *c)?;
+//! assert_eq!("3 . . .\n2 . X .\n1 . . .\n  a b c\n", storage.as_str().unwrap());
+//! # Ok::<(), surena_game::Error>(())
+//! ```
+
+use crate::{move_code, Error, ErrorCode, Result, StrBuf};
+
+use std::{
+    array,
+    fmt::Write,
+    ops::{Index, IndexMut},
+};
+
+/// Number of letters available for files.
+const MAX_FILES: i32 = 26;
+
+/// Position on a board.
+///
+/// `x` is the file (column) and `y` the rank (row), both starting at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Coord {
+    #[inline]
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Returns this coordinate moved by `dx` and `dy`.
+    #[inline]
+    pub const fn offset(self, (dx, dy): (i32, i32)) -> Self {
+        Self::new(self.x + dx, self.y + dy)
+    }
+}
+
+/// Neighborhood of the cells of a [`Grid`].
+pub trait Topology {
+    /// Offsets of all neighbors.
+    const DIRECTIONS: &'static [(i32, i32)];
+
+    /// Number of spaces in front of row `y` when printing a board of height
+    /// `height`.
+    #[allow(unused_variables)]
+    fn indent(y: i32, height: i32) -> usize {
+        0
+    }
+}
+
+/// Square cells with four orthogonal neighbors.
+pub struct Square4;
+
+impl Topology for Square4 {
+    const DIRECTIONS: &'static [(i32, i32)] = &[(1, 0), (0, 1), (-1, 0), (0, -1)];
+}
+
+/// Square cells with orthogonal and diagonal neighbors.
+pub struct Square8;
+
+impl Topology for Square8 {
+    const DIRECTIONS: &'static [(i32, i32)] = &[
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+    ];
+}
+
+/// Hexagonal cells on a rhombus-shaped board.
+///
+/// Besides the cells to the left and right, `(x, y)` touches `(x, y ± 1)`,
+/// `(x + 1, y + 1)`, and `(x - 1, y - 1)`.
+/// When printing, each row is shifted to the right by one more space than
+/// the row above.
+pub struct Hex;
+
+impl Topology for Hex {
+    const DIRECTIONS: &'static [(i32, i32)] = &[(1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1), (0, -1)];
+
+    fn indent(y: i32, height: i32) -> usize {
+        (height - 1 - y) as usize
+    }
+}
+
+/// Board of `W`×`H` cells of type `T`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<T, const W: usize, const H: usize> {
+    cells: [[T; W]; H],
+}
+
+impl<T, const W: usize, const H: usize> Grid<T, W, H> {
+    pub const WIDTH: i32 = W as i32;
+    pub const HEIGHT: i32 = H as i32;
+    /// Number of cells.
+    pub const SIZE: usize = W * H;
+    /// Fails to compile if there are more files than letters.
+    const NOTATION: () = assert!(W as i32 <= MAX_FILES, "at most 26 files are supported");
+
+    /// Creates a grid by calling `f` for each coordinate.
+    pub fn from_fn(mut f: impl FnMut(Coord) -> T) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::NOTATION;
+        Self {
+            cells: array::from_fn(|y| array::from_fn(|x| f(Coord::new(x as i32, y as i32)))),
+        }
+    }
+
+    /// Returns whether `coord` is on the board.
+    #[inline]
+    pub fn contains(coord: Coord) -> bool {
+        (0..Self::WIDTH).contains(&coord.x) && (0..Self::HEIGHT).contains(&coord.y)
+    }
+
+    #[inline]
+    pub fn get(&self, coord: Coord) -> Option<&T> {
+        Self::contains(coord).then(|| &self.cells[coord.y as usize][coord.x as usize])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, coord: Coord) -> Option<&mut T> {
+        Self::contains(coord).then(|| &mut self.cells[coord.y as usize][coord.x as usize])
+    }
+
+    /// Iterates over all coordinates in row-major order.
+    pub fn coords() -> impl Iterator<Item = Coord> {
+        (0..Self::HEIGHT).flat_map(|y| (0..Self::WIDTH).map(move |x| Coord::new(x, y)))
+    }
+
+    /// Iterates over all cells and their coordinates in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, &T)> {
+        Self::coords().zip(self.cells.iter().flatten())
+    }
+
+    /// Iterates over the neighbors of `coord` which are on the board.
+    pub fn neighbors<N: Topology>(&self, coord: Coord) -> impl Iterator<Item = Coord> {
+        N::DIRECTIONS
+            .iter()
+            .map(move |&d| coord.offset(d))
+            .filter(|&c| Self::contains(c))
+    }
+
+    /// Packs `coord` into a [`move_code`] below [`Self::SIZE`].
+    ///
+    /// # Panics
+    /// Panics if `coord` is not on the board.
+    #[inline]
+    pub fn encode(coord: Coord) -> move_code {
+        assert!(Self::contains(coord), "coordinate is not on the board");
+        (coord.y * Self::WIDTH + coord.x) as move_code
+    }
+
+    /// Inverse of [`Self::encode`].
+    pub fn decode(mov: move_code) -> Result<Coord> {
+        if mov >= Self::SIZE as move_code {
+            return Err(Error::new_static(
+                ErrorCode::InvalidMove,
+                "move is not on the board\0",
+            ));
+        }
+        let index = mov as i32;
+        Ok(Coord::new(index % Self::WIDTH, index / Self::WIDTH))
+    }
+
+    /// Packs a move from `from` to `to` into a [`move_code`].
+    ///
+    /// # Panics
+    /// Panics if a coordinate is not on the board.
+    #[inline]
+    pub fn encode_step(from: Coord, to: Coord) -> move_code {
+        Self::encode(from) * Self::SIZE as move_code + Self::encode(to)
+    }
+
+    /// Inverse of [`Self::encode_step`].
+    pub fn decode_step(mov: move_code) -> Result<(Coord, Coord)> {
+        let size = Self::SIZE as move_code;
+        Ok((Self::decode(mov / size)?, Self::decode(mov % size)?))
+    }
+
+    /// Parses a coordinate like `"e4"` into a [`move_code`].
+    ///
+    /// This can be used for
+    /// [`GameMethods::get_move_code`](crate::GameMethods::get_move_code).
+    pub fn coord_move_code(string: &str) -> Result<move_code> {
+        Ok(Self::encode(Self::parse_on_board(string)?))
+    }
+
+    /// Writes the coordinate encoded in `mov`.
+    ///
+    /// This can be used for
+    /// [`GameMethods::get_move_str`](crate::GameMethods::get_move_str).
+    pub fn coord_move_str(mov: move_code, str_buf: &mut StrBuf) -> Result<()> {
+        write_coord(str_buf, Self::decode(mov)?)
+    }
+
+    /// Parses a move like `"c3-d5"` into a [`move_code`].
+    pub fn step_move_code(string: &str) -> Result<move_code> {
+        let (from, to) = split_step(string)?;
+        Ok(Self::encode_step(
+            Self::parse_on_board(from)?,
+            Self::parse_on_board(to)?,
+        ))
+    }
+
+    /// Writes the move encoded in `mov` like `"c3-d5"`.
+    pub fn step_move_str(mov: move_code, str_buf: &mut StrBuf) -> Result<()> {
+        let (from, to) = Self::decode_step(mov)?;
+        write_step(str_buf, from, to)
+    }
+
+    /// Size of coordinate strings including the NUL byte.
+    ///
+    /// This can be used as
+    /// [`buf_sizer::move_str`](crate::buf_sizer::move_str).
+    pub fn coord_str_size() -> usize {
+        1 + digits(H) + 1
+    }
+
+    /// Size of move strings like `"c3-d5"` including the NUL byte.
+    pub fn step_str_size() -> usize {
+        2 * (1 + digits(H)) + 2
+    }
+
+    /// Size of the output of [`Self::print`] including the NUL byte.
+    ///
+    /// This can be used as
+    /// [`buf_sizer::print_str`](crate::buf_sizer::print_str).
+    pub fn print_str_size<N: Topology>() -> usize {
+        let indent = (0..Self::HEIGHT)
+            .map(|y| N::indent(y, Self::HEIGHT))
+            .max()
+            .unwrap_or_default();
+        // Rank label, indent, cells with spaces, and newline for each row
+        // plus the file labels.
+        (H + 1) * (digits(H) + indent + 2 * W + 1) + 1
+    }
+
+    /// Prints the board with the highest rank on top and labels.
+    ///
+    /// `cell` returns the character representing a cell.
+    pub fn print<N: Topology>(
+        &self,
+        str_buf: &mut StrBuf,
+        cell: impl Fn(&T) -> char,
+    ) -> Result<()> {
+        let width = digits(H);
+        for y in (0..Self::HEIGHT).rev() {
+            let indent = N::indent(y, Self::HEIGHT);
+            write!(str_buf, "{:>width$} {:indent$}", y + 1, "").map_err(|_| full())?;
+            for (x, value) in self.cells[y as usize].iter().enumerate() {
+                let separator = if x + 1 < W { " " } else { "\n" };
+                write!(str_buf, "{}{separator}", cell(value)).map_err(|_| full())?;
+            }
+        }
+
+        let indent = N::indent(0, Self::HEIGHT);
+        write!(str_buf, "{:width$} {:indent$}", "", "").map_err(|_| full())?;
+        for x in 0..Self::WIDTH {
+            let separator = if x + 1 < Self::WIDTH { " " } else { "\n" };
+            write!(str_buf, "{}{separator}", file_char(x)?).map_err(|_| full())?;
+        }
+        Ok(())
+    }
+
+    fn parse_on_board(string: &str) -> Result<Coord> {
+        let coord = parse_coord(string)?;
+        if !Self::contains(coord) {
+            return Err(Error::new_dynamic(
+                ErrorCode::InvalidInput,
+                format!("coordinate \"{string}\" is not on the board"),
+            ));
+        }
+        Ok(coord)
+    }
+}
+
+impl<T: Clone, const W: usize, const H: usize> Grid<T, W, H> {
+    /// Creates a grid with all cells set to `value`.
+    pub fn filled(value: T) -> Self {
+        Self::from_fn(|_| value.clone())
+    }
+}
+
+impl<T: Default, const W: usize, const H: usize> Default for Grid<T, W, H> {
+    fn default() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+impl<T, const W: usize, const H: usize> Index<Coord> for Grid<T, W, H> {
+    type Output = T;
+
+    /// # Panics
+    /// Panics if `coord` is not on the board.
+    #[inline]
+    fn index(&self, coord: Coord) -> &Self::Output {
+        self.get(coord).expect("coordinate is not on the board")
+    }
+}
+
+impl<T, const W: usize, const H: usize> IndexMut<Coord> for Grid<T, W, H> {
+    #[inline]
+    fn index_mut(&mut self, coord: Coord) -> &mut Self::Output {
+        self.get_mut(coord).expect("coordinate is not on the board")
+    }
+}
+
+/// Parses a coordinate in algebraic notation like `"e4"`.
+///
+/// Ranks with leading zeros are rejected, so that every coordinate has
+/// exactly one notation.
+///
+/// # Example
+/// ```
+/// # use surena_game::board::*;
+/// assert_eq!(Coord::new(4, 3), parse_coord("e4")?);
+/// assert!(parse_coord("e04").is_err());
+/// assert!(parse_coord("e0").is_err());
+/// # Ok::<(), surena_game::Error>(())
+/// ```
+pub fn parse_coord(string: &str) -> Result<Coord> {
+    let invalid = || {
+        Error::new_dynamic(
+            ErrorCode::InvalidInput,
+            format!("invalid coordinate \"{string}\""),
+        )
+    };
+
+    let mut chars = string.chars();
+    let file = chars.next().ok_or_else(invalid)?;
+    if !file.is_ascii_lowercase() {
+        return Err(invalid());
+    }
+    let rank = chars.as_str();
+    if !rank.bytes().all(|b| b.is_ascii_digit()) || rank.starts_with('0') {
+        return Err(invalid());
+    }
+    let rank: i32 = rank.parse().map_err(|_| invalid())?;
+    Ok(Coord::new(i32::from(file as u8 - b'a'), rank - 1))
+}
+
+/// Writes `coord` in algebraic notation like `"e4"`.
+pub fn write_coord(str_buf: &mut StrBuf, coord: Coord) -> Result<()> {
+    if coord.y < 0 {
+        return Err(Error::new_static(
+            ErrorCode::InvalidInput,
+            "coordinate has no notation\0",
+        ));
+    }
+    write!(str_buf, "{}{}", file_char(coord.x)?, coord.y + 1).map_err(|_| full())
+}
+
+/// Parses a move between two coordinates like `"c3-d5"`.
+pub fn parse_step(string: &str) -> Result<(Coord, Coord)> {
+    let (from, to) = split_step(string)?;
+    Ok((parse_coord(from)?, parse_coord(to)?))
+}
+
+/// Writes a move between two coordinates like `"c3-d5"`.
+pub fn write_step(str_buf: &mut StrBuf, from: Coord, to: Coord) -> Result<()> {
+    write_coord(str_buf, from)?;
+    str_buf.write_char('-').map_err(|_| full())?;
+    write_coord(str_buf, to)
+}
+
+fn split_step(string: &str) -> Result<(&str, &str)> {
+    string.split_once('-').ok_or_else(|| {
+        Error::new_dynamic(
+            ErrorCode::InvalidInput,
+            format!("move \"{string}\" is missing a '-'"),
+        )
+    })
+}
+
+fn file_char(x: i32) -> Result<char> {
+    if !(0..MAX_FILES).contains(&x) {
+        return Err(Error::new_static(
+            ErrorCode::InvalidInput,
+            "coordinate has no notation\0",
+        ));
+    }
+    Ok(char::from(b'a' + x as u8))
+}
+
+fn full() -> Error {
+    Error::new_static(ErrorCode::OutOfMemory, "string buffer is full\0")
+}
+
+/// Calculates the number of digits needed to print `n`.
+const fn digits(mut n: usize) -> usize {
+    let mut digits = 1;
+    while n >= 10 {
+        n /= 10;
+        digits += 1;
+    }
+    digits
+}
@@ -0,0 +1,679 @@
+//! Packing of structured moves into [`move_code`]s.
+//!
+//! Types implementing [`MoveCodec`] are packed into the lowest
+//! [`MoveCodec::BITS`] bits of a [`move_code`].
+//! At most 63 bits can be used, so that [`MOVE_NONE`] is never produced.
+//! Structs and enums can implement [`MoveCodec`] via the
+//! [`move_codec!`](crate::move_codec) macro.
+//!
+//! Games implementing [`TypedGame`] receive decoded moves.
+//! They can be exported by wrapping them in [`Typed`].
+//!
+//! # Example
+//! ```
+//! use surena_game::{codec::*, move_codec};
+//!
+//! move_codec! {
+//!     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+//!     pub enum Piece {
+//!         Queen,
+//!         Rook,
+//!         Bishop,
+//!         Knight,
+//!     }
+//! }
+//!
+//! move_codec! {
+//!     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+//!     pub struct ChessMove {
+//!         pub from: u8,
+//!         pub to: u8,
+//!         pub promotion: Option<Piece>,
+//!     }
+//! }
+//!
+//! assert_eq!(8 + 8 + 1 + 2, ChessMove::BITS);
+//! let mov = ChessMove { from: 52, to: 60, promotion: Some(Piece::Knight) };
+//! let code = encode(&mov);
+//! assert_eq!(mov, decode(code)?);
+//! assert!(decode::<ChessMove>(1 << ChessMove::BITS).is_err());
+//! # Ok::<(), surena_game::Error>(())
+//! ```
+
+use crate::{
+    buf_sizer, move_code, player_id, sync_counter, Error, ErrorCode, GameInit, GameMethods, PtrVec,
+    Result, StrBuf, MOVE_NONE,
+};
+
+use std::marker::PhantomData;
+
+/// A move type which can be packed into a [`move_code`].
+pub trait MoveCodec: Sized {
+    /// Number of bits used by [`Self::pack`].
+    const BITS: u32;
+
+    /// Returns the bits representing `self`.
+    ///
+    /// Only the lowest [`Self::BITS`] bits may be set.
+    fn pack(&self) -> u64;
+    /// Inverse of [`Self::pack`].
+    ///
+    /// Returns an error if `bits` do not represent a valid move.
+    fn unpack(bits: u64) -> Result<Self>;
+}
+
+/// Packs `mov` into a [`move_code`].
+///
+/// # Panics
+/// Panics if `M` uses more than 63 bits or [`MoveCodec::pack`] sets bits
+/// above [`MoveCodec::BITS`], as [`MOVE_NONE`] could be produced otherwise.
+#[inline]
+pub fn encode<M: MoveCodec>(mov: &M) -> move_code {
+    assert!(M::BITS < u64::BITS, "MoveCodec uses more than 63 bits");
+    let code = mov.pack();
+    assert_eq!(0, code >> M::BITS, "MoveCodec packed bits above BITS");
+    code
+}
+
+/// Unpacks `code` into an `M`.
+///
+/// Returns an [`ErrorCode::InvalidMove`] error for [`MOVE_NONE`] and codes
+/// with bits above [`MoveCodec::BITS`].
+#[inline]
+pub fn decode<M: MoveCodec>(code: move_code) -> Result<M> {
+    if code == MOVE_NONE || code.checked_shr(M::BITS).unwrap_or(0) != 0 {
+        return Err(Error::new_static(
+            ErrorCode::InvalidMove,
+            "move code out of range\0",
+        ));
+    }
+    M::unpack(code)
+}
+
+/// Allows passing raw [`move_code`]s through [`Typed`].
+///
+/// Only the lowest 63 bits are available, so [`decode`] rejects codes with
+/// the highest bit set.
+impl MoveCodec for move_code {
+    const BITS: u32 = 63;
+
+    #[inline]
+    fn pack(&self) -> u64 {
+        *self
+    }
+
+    #[inline]
+    fn unpack(bits: u64) -> Result<Self> {
+        Ok(bits)
+    }
+}
+
+macro_rules! impl_move_codec_int {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl MoveCodec for $t {
+                const BITS: u32 = <$t>::BITS;
+
+                #[inline]
+                fn pack(&self) -> u64 {
+                    (*self).into()
+                }
+
+                #[inline]
+                fn unpack(bits: u64) -> Result<Self> {
+                    Ok(bits as $t)
+                }
+            }
+        )*
+    };
+}
+
+impl_move_codec_int!(u8, u16, u32);
+
+impl MoveCodec for bool {
+    const BITS: u32 = 1;
+
+    #[inline]
+    fn pack(&self) -> u64 {
+        (*self).into()
+    }
+
+    #[inline]
+    fn unpack(bits: u64) -> Result<Self> {
+        Ok(bits != 0)
+    }
+}
+
+impl<T: MoveCodec> MoveCodec for Option<T> {
+    /// The lowest bit stores whether a value is present.
+    const BITS: u32 = T::BITS + 1;
+
+    #[inline]
+    fn pack(&self) -> u64 {
+        match self {
+            None => 0,
+            Some(value) => (value.pack() << 1) | 1,
+        }
+    }
+
+    #[inline]
+    fn unpack(bits: u64) -> Result<Self> {
+        match bits & 1 {
+            0 if bits == 0 => Ok(None),
+            0 => Err(Error::new_static(
+                ErrorCode::InvalidMove,
+                "invalid empty move component\0",
+            )),
+            _ => T::unpack(bits >> 1).map(Some),
+        }
+    }
+}
+
+/// Packs multiple [`MoveCodec`] values into consecutive bits.
+///
+/// The first value occupies the lowest bits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Packer {
+    bits: u64,
+    shift: u32,
+}
+
+impl Packer {
+    /// # Panics
+    /// Panics if the pushed values use more than 64 bits.
+    #[inline]
+    pub fn push<T: MoveCodec>(&mut self, value: &T) {
+        assert!(self.shift + T::BITS <= u64::BITS, "packed move too large");
+        self.bits |= value.pack().checked_shl(self.shift).unwrap_or(0);
+        self.shift += T::BITS;
+    }
+
+    #[inline]
+    pub fn finish(self) -> u64 {
+        self.bits
+    }
+}
+
+/// Unpacks values in the order they were pushed into a [`Packer`].
+#[derive(Clone, Copy, Debug)]
+pub struct Unpacker {
+    bits: u64,
+}
+
+impl Unpacker {
+    #[inline]
+    pub fn new(bits: u64) -> Self {
+        Self { bits }
+    }
+
+    #[inline]
+    pub fn pop<T: MoveCodec>(&mut self) -> Result<T> {
+        let mask = 1u64.checked_shl(T::BITS).map_or(u64::MAX, |m| m - 1);
+        let value = T::unpack(self.bits & mask)?;
+        self.bits = self.bits.checked_shr(T::BITS).unwrap_or(0);
+        Ok(value)
+    }
+}
+
+/// This macro declares a struct or a fieldless enum and implements
+/// [`MoveCodec`] for it.
+///
+/// Struct fields are packed in declaration order using a [`Packer`] and must
+/// implement [`MoveCodec`] themselves.
+/// Enum variants are packed as their index using as few bits as possible.
+/// Enums need to implement [`Clone`].
+///
+/// See the [module documentation](crate::codec) for an example.
+#[macro_export]
+macro_rules! move_codec {
+    (
+        $( #[$meta:meta] )*
+        $vis:vis struct $name:ident {
+            $( $( #[$fmeta:meta] )* $fvis:vis $field:ident : $t:ty ),* $(,)?
+        }
+    ) => {
+        $( #[$meta] )*
+        $vis struct $name {
+            $( $( #[$fmeta] )* $fvis $field: $t ),*
+        }
+
+        impl $crate::codec::MoveCodec for $name {
+            const BITS: u32 = 0 $( + <$t as $crate::codec::MoveCodec>::BITS )*;
+
+            fn pack(&self) -> u64 {
+                let mut packer = $crate::codec::Packer::default();
+                $( packer.push(&self.$field); )*
+                packer.finish()
+            }
+
+            fn unpack(bits: u64) -> $crate::Result<Self> {
+                let mut unpacker = $crate::codec::Unpacker::new(bits);
+                // Fields are evaluated in declaration order.
+                Ok(Self { $( $field: unpacker.pop()? ),* })
+            }
+        }
+    };
+    (
+        $( #[$meta:meta] )*
+        $vis:vis enum $name:ident {
+            $( $( #[$vmeta:meta] )* $variant:ident ),* $(,)?
+        }
+    ) => {
+        $( #[$meta] )*
+        $vis enum $name {
+            $( $( #[$vmeta] )* $variant ),*
+        }
+
+        impl $name {
+            const VARIANTS: &'static [Self] = &[$( Self::$variant ),*];
+        }
+
+        impl $crate::codec::MoveCodec for $name {
+            const BITS: u32 = (Self::VARIANTS.len() as u64)
+                .next_power_of_two()
+                .trailing_zeros();
+
+            fn pack(&self) -> u64 {
+                // Only fieldless variants are accepted.
+                Self::VARIANTS
+                    .iter()
+                    .position(|v| ::std::mem::discriminant(v) == ::std::mem::discriminant(self))
+                    .expect("all variants are listed") as u64
+            }
+
+            fn unpack(bits: u64) -> $crate::Result<Self> {
+                usize::try_from(bits)
+                    .ok()
+                    .and_then(|i| Self::VARIANTS.get(i))
+                    .cloned()
+                    .ok_or_else(|| {
+                        $crate::Error::new_static(
+                            $crate::ErrorCode::InvalidMove,
+                            "invalid move variant\0",
+                        )
+                    })
+            }
+        }
+    };
+}
+
+/// Alternative to [`GameMethods`] which receives and returns typed moves.
+///
+/// Export it by wrapping it in [`Typed`].
+/// Optional methods must be implemented when their feature flag is enabled,
+/// as for [`GameMethods`].
+/// Hidden information, actions, legacy, and custom playouts are not
+/// supported.
+pub trait TypedGame: Sized + Clone + Eq + Send {
+    type Move: MoveCodec;
+
+    fn create(init_info: &GameInit) -> Result<(Self, buf_sizer)>;
+    fn copy_from(&mut self, other: &mut Self) -> Result<()>;
+    fn import_state(&mut self, string: Option<&str>) -> Result<()>;
+    fn export_state(&mut self, str_buf: &mut StrBuf) -> Result<()>;
+    fn players_to_move(&mut self, players: &mut PtrVec<player_id>) -> Result<()>;
+    fn get_concrete_moves(
+        &mut self,
+        player: player_id,
+        moves: &mut MoveVec<Self::Move>,
+    ) -> Result<()>;
+    fn get_move_code(&mut self, player: player_id, string: &str) -> Result<Self::Move>;
+    fn get_move_str(
+        &mut self,
+        player: player_id,
+        mov: Self::Move,
+        str_buf: &mut StrBuf,
+    ) -> Result<()>;
+    fn make_move(
+        &mut self,
+        player: player_id,
+        mov: Self::Move,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()>;
+    fn get_results(&mut self, players: &mut PtrVec<player_id>) -> Result<()>;
+    #[allow(clippy::wrong_self_convention)]
+    fn is_legal_move(
+        &mut self,
+        player: player_id,
+        mov: Self::Move,
+        sync_ctr: sync_counter,
+    ) -> Result<()>;
+
+    /// See [`GameMethods::export_options`].
+    #[allow(unused_variables)]
+    fn export_options(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        unimplemented!("export_options")
+    }
+    /// See [`GameMethods::print`].
+    #[allow(unused_variables)]
+    fn print(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        unimplemented!("print")
+    }
+    /// See [`GameMethods::serialize`].
+    #[allow(unused_variables)]
+    fn serialize(&mut self, buf: &mut PtrVec<u8>) -> Result<()> {
+        unimplemented!("serialize")
+    }
+    /// See [`GameMethods::get_concrete_move_probabilities`].
+    #[allow(unused_variables)]
+    fn get_concrete_move_probabilities(
+        &mut self,
+        player: player_id,
+        probabilities: &mut PtrVec<f32>,
+    ) -> Result<()> {
+        unimplemented!("get_concrete_move_probabilities")
+    }
+    /// See [`GameMethods::get_random_move`].
+    #[allow(unused_variables)]
+    fn get_random_move(&mut self, seed: u64) -> Result<Self::Move> {
+        unimplemented!("get_random_move")
+    }
+    /// See [`GameMethods::discretize`].
+    #[allow(unused_variables)]
+    fn discretize(&mut self, seed: u64) -> Result<()> {
+        unimplemented!("discretize")
+    }
+    /// See [`GameMethods::get_move_ordering`].
+    #[allow(unused_variables)]
+    fn get_move_ordering(
+        &mut self,
+        player: player_id,
+        moves: &mut MoveVec<Self::Move>,
+    ) -> Result<()> {
+        unimplemented!("get_move_ordering")
+    }
+    /// See [`GameMethods::get_scores`].
+    #[allow(unused_variables)]
+    fn get_scores(&mut self, scores: &mut PtrVec<i32>) -> Result<()> {
+        unimplemented!("get_scores")
+    }
+    /// See [`GameMethods::get_id`].
+    fn get_id(&mut self) -> Result<u64> {
+        unimplemented!("get_id")
+    }
+    /// See [`GameMethods::get_eval`].
+    #[allow(unused_variables)]
+    fn get_eval(&mut self, player: player_id) -> Result<f32> {
+        unimplemented!("get_eval")
+    }
+}
+
+/// [`PtrVec`] of [`move_code`]s which accepts typed moves `M`.
+pub struct MoveVec<'a, 'b, M: MoveCodec> {
+    moves: &'a mut PtrVec<'b, move_code>,
+    _move: PhantomData<M>,
+}
+
+impl<'a, 'b, M: MoveCodec> MoveVec<'a, 'b, M> {
+    #[inline]
+    fn new(moves: &'a mut PtrVec<'b, move_code>) -> Self {
+        Self {
+            moves,
+            _move: PhantomData,
+        }
+    }
+
+    /// Encodes `mov` and appends it.
+    ///
+    /// # Panics
+    /// Panics if the vector is full.
+    #[inline]
+    pub fn push(&mut self, mov: &M) {
+        self.moves.push(encode(mov));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.moves.capacity()
+    }
+}
+
+/// Adapter implementing [`GameMethods`] for a [`TypedGame`].
+///
+/// Move codes which cannot be decoded are rejected with
+/// [`ErrorCode::InvalidMove`].
+///
+/// # Example
+/// ```ignore
+/// create_game_methods::<Typed<MyGame>>(metadata);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Typed<G>(pub G);
+
+impl<G: TypedGame> GameMethods for Typed<G> {
+    fn create(init_info: &GameInit) -> Result<(Self, buf_sizer)> {
+        G::create(init_info).map(|(game, sizer)| (Self(game), sizer))
+    }
+
+    fn copy_from(&mut self, other: &mut Self) -> Result<()> {
+        self.0.copy_from(&mut other.0)
+    }
+
+    fn import_state(&mut self, string: Option<&str>) -> Result<()> {
+        self.0.import_state(string)
+    }
+
+    fn export_state(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        self.0.export_state(str_buf)
+    }
+
+    fn players_to_move(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        self.0.players_to_move(players)
+    }
+
+    fn get_concrete_moves(
+        &mut self,
+        player: player_id,
+        moves: &mut PtrVec<move_code>,
+    ) -> Result<()> {
+        self.0.get_concrete_moves(player, &mut MoveVec::new(moves))
+    }
+
+    fn get_move_code(&mut self, player: player_id, string: &str) -> Result<move_code> {
+        self.0.get_move_code(player, string).map(|mov| encode(&mov))
+    }
+
+    fn get_move_str(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        str_buf: &mut StrBuf,
+    ) -> Result<()> {
+        self.0.get_move_str(player, decode(mov)?, str_buf)
+    }
+
+    fn make_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
+        self.0.make_move(player, decode(mov)?, sync_ctr)
+    }
+
+    fn get_results(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        self.0.get_results(players)
+    }
+
+    fn is_legal_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: sync_counter,
+    ) -> Result<()> {
+        self.0.is_legal_move(player, decode(mov)?, sync_ctr)
+    }
+
+    fn export_options(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        self.0.export_options(str_buf)
+    }
+
+    fn print(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        self.0.print(str_buf)
+    }
+
+    fn serialize(&mut self, buf: &mut PtrVec<u8>) -> Result<()> {
+        self.0.serialize(buf)
+    }
+
+    fn get_concrete_move_probabilities(
+        &mut self,
+        player: player_id,
+        probabilities: &mut PtrVec<f32>,
+    ) -> Result<()> {
+        self.0
+            .get_concrete_move_probabilities(player, probabilities)
+    }
+
+    fn get_random_move(&mut self, seed: u64) -> Result<move_code> {
+        self.0.get_random_move(seed).map(|mov| encode(&mov))
+    }
+
+    fn discretize(&mut self, seed: u64) -> Result<()> {
+        self.0.discretize(seed)
+    }
+
+    fn get_move_ordering(
+        &mut self,
+        player: player_id,
+        moves: &mut PtrVec<move_code>,
+    ) -> Result<()> {
+        self.0.get_move_ordering(player, &mut MoveVec::new(moves))
+    }
+
+    fn get_scores(&mut self, scores: &mut PtrVec<i32>) -> Result<()> {
+        self.0.get_scores(scores)
+    }
+
+    fn get_id(&mut self) -> Result<u64> {
+        self.0.get_id()
+    }
+
+    fn get_eval(&mut self, player: player_id) -> Result<f32> {
+        self.0.get_eval(player)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    move_codec! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        enum Three {
+            A,
+            B,
+            C,
+        }
+    }
+
+    move_codec! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        struct Step {
+            from: u8,
+            flag: bool,
+            to: Option<Three>,
+        }
+    }
+
+    move_codec! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        struct Wide {
+            code: move_code,
+            flag: bool,
+        }
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(9, Option::<u8>::BITS);
+        assert_eq!(0, encode(&None::<u8>));
+        assert_eq!(0b1011, encode(&Some(5u8)));
+        assert_eq!(None, decode::<Option<u8>>(0).unwrap());
+        assert_eq!(Some(5), decode::<Option<u8>>(0b1011).unwrap());
+        // Without the presence bit, only zero is valid.
+        assert!(decode::<Option<u8>>(0b1010).is_err());
+        assert!(decode::<Option<u8>>(1 << 9).is_err());
+    }
+
+    #[test]
+    fn enumeration() {
+        assert_eq!(2, Three::BITS);
+        for (i, value) in [Three::A, Three::B, Three::C].into_iter().enumerate() {
+            assert_eq!(i as move_code, encode(&value));
+            assert_eq!(value, decode(i as move_code).unwrap());
+        }
+        // The bits fit but do not name a variant.
+        assert!(decode::<Three>(3).is_err());
+        assert!(decode::<Three>(4).is_err());
+    }
+
+    #[test]
+    fn structure() {
+        assert_eq!(8 + 1 + 3, Step::BITS);
+        let step = Step {
+            from: 0xAB,
+            flag: true,
+            to: Some(Three::C),
+        };
+        // Fields occupy consecutive bits starting with the first one.
+        let code = encode(&step);
+        assert_eq!(0xAB | 1 << 8 | 0b101 << 9, code);
+        assert_eq!(step, decode(code).unwrap());
+        // Invalid fields are rejected.
+        assert!(decode::<Step>(0b111 << 9).is_err());
+        assert!(decode::<Step>(1 << Step::BITS).is_err());
+    }
+
+    #[test]
+    fn move_none_is_reserved() {
+        assert!(decode::<move_code>(MOVE_NONE).is_err());
+        assert!(decode::<move_code>(1 << 63).is_err());
+        let max = u64::MAX >> 1;
+        assert_ne!(MOVE_NONE, encode(&max));
+        assert_eq!(max, decode::<move_code>(max).unwrap());
+    }
+
+    #[test]
+    fn unpack_masks_components() {
+        let mut unpacker = Unpacker::new(0x1FF);
+        assert_eq!(0xFF, unpacker.pop::<u8>().unwrap());
+        assert!(unpacker.pop::<bool>().unwrap());
+        assert_eq!(0, unpacker.pop::<u32>().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 63 bits")]
+    fn encode_rejects_wide_moves() {
+        assert_eq!(64, Wide::BITS);
+        encode(&Wide {
+            code: 0,
+            flag: false,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "packed bits above BITS")]
+    fn encode_rejects_bits_above_width() {
+        encode(&u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "packed move too large")]
+    fn packer_rejects_overflow() {
+        let mut packer = Packer::default();
+        packer.push(&u32::MAX);
+        packer.push(&u32::MAX);
+        packer.push(&true);
+    }
+}
@@ -0,0 +1,757 @@
+//! Wrapper for the engine API of _surena_.
+//!
+//! _surena_ engines run in their own thread and communicate with the host via
+//! event queues.
+//! The wrapper spawns this thread, translates the incoming events into calls
+//! of [`EngineMethods`], and provides an [`Outbox`] for sending events back.
+
+pub use mirabel_sys::sys::{engine_feature_flags, engine_methods};
+
+use crate::{
+    game_methods, move_code, panic_message, player_id, semver, sys, Error, ErrorCode, ErrorString,
+    GameInit, Result, ValidCStr,
+};
+use mirabel_sys::cstr_to_rust;
+
+use std::{
+    ffi::{c_void, CString},
+    mem::MaybeUninit,
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::{addr_of, addr_of_mut, null_mut},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Time in milliseconds to wait for new events while no search is running.
+const POLL_TIMEOUT: u32 = 100;
+
+/// This macro creates the `plugin_get_engine_methods` function.
+///
+/// It works exactly like [`plugin_get_game_methods!`](crate::plugin_get_game_methods)
+/// but for [`engine_methods`].
+/// These can be generated using [`create_engine_methods`].
+/// It also exports the `plugin_init_engine`, `plugin_get_engine_capi_version`,
+/// and `plugin_cleanup_engine` functions for you.
+///
+/// # Example
+/// ```ignore
+/// plugin_get_engine_methods!(create_engine_methods::<MyEngine>(metadata));
+/// ```
+#[macro_export]
+macro_rules! plugin_get_engine_methods {
+    ( $( $x:expr ),* ) => {
+        static mut PLUGIN_ENGINE_METHODS: ::std::mem::MaybeUninit<
+            [$crate::sys::engine_methods; $crate::count!($($x),*)]
+        > = ::std::mem::MaybeUninit::uninit();
+
+        #[no_mangle]
+        unsafe extern "C" fn plugin_init_engine() {
+            ::std::mem::MaybeUninit::write(
+                &mut self::PLUGIN_ENGINE_METHODS, [$($x),*]
+            );
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_get_engine_methods(
+            count: *mut u32,
+            methods: *mut *const $crate::sys::engine_methods,
+        ) {
+            count.write($crate::count!($($x),*));
+            if methods.is_null() {
+                return;
+            }
+
+            let src = ::std::mem::MaybeUninit::assume_init_ref(
+                &self::PLUGIN_ENGINE_METHODS
+            );
+            for i in 0..$crate::count!($($x),*) {
+                methods.add(i).write(&src[i]);
+            }
+        }
+
+        #[no_mangle]
+        unsafe extern "C" fn plugin_cleanup_engine() {
+            // The static array of C structs does not need cleanup.
+        }
+
+        /// This exports the engine API version to the outside.
+        #[no_mangle]
+        pub extern "C" fn plugin_get_engine_capi_version() -> u64 {
+            $crate::sys::SURENA_ENGINE_API_VERSION
+        }
+    };
+}
+
+/// Main trait which needs to be implemented by your engine struct.
+///
+/// See `engine.h` @ _surena_ for API documentation.
+/// You should **not implement `[...]_wrapped`** methods.
+///
+/// All methods except [`Self::create`] and [`Self::is_game_compatible`] are
+/// called on the engine thread.
+/// Errors returned from these are reported to the host as log events.
+/// A panic is reported in the same way and stops the engine thread, which
+/// then ignores all further events.
+/// Engines need to implement [`Drop`] for custom `destroy` handling.
+pub trait EngineMethods: Sized + Send + 'static {
+    fn create() -> Result<Self>;
+    /// Returns an error if this engine cannot play the game described by
+    /// `methods`.
+    fn is_game_compatible(methods: &game_methods) -> Result<()>;
+    /// Loads a new game which replaces the current one.
+    ///
+    /// `methods` stay valid as long as the game plugin is loaded.
+    fn game_load(
+        &mut self,
+        methods: &game_methods,
+        init_info: &GameInit,
+        out: &mut Outbox,
+    ) -> Result<()>;
+    fn game_state(&mut self, state: Option<&str>, out: &mut Outbox) -> Result<()>;
+    fn game_move(&mut self, player: player_id, mov: move_code, out: &mut Outbox) -> Result<()>;
+    /// Starts searching for the best move of `player`.
+    ///
+    /// The search should be performed incrementally in
+    /// [`Self::search_step`], so that incoming events can still be processed.
+    /// The best move is reported via [`Outbox::best_move`].
+    fn search_start(
+        &mut self,
+        player: player_id,
+        timeout: Option<Duration>,
+        out: &mut Outbox,
+    ) -> Result<()>;
+    fn search_stop(&mut self, out: &mut Outbox) -> Result<()>;
+
+    /// Unloads the current game.
+    #[allow(unused_variables)]
+    fn game_unload(&mut self, out: &mut Outbox) -> Result<()> {
+        Ok(())
+    }
+    /// Called repeatedly while a search is running and no events are pending.
+    ///
+    /// Returns whether the search is still running.
+    #[allow(unused_variables)]
+    fn search_step(&mut self, out: &mut Outbox) -> Result<bool> {
+        Ok(false)
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_last_error_wrapped(engine: *mut sys::engine) -> *const c_char {
+        (&Aux::get(engine).error).into()
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn create_wrapped(
+        engine: *mut sys::engine,
+        outbox: *mut sys::eevent_queue,
+        inbox: *mut sys::eevent_queue,
+    ) -> sys::error_code {
+        Aux::init(engine);
+        let aux = Aux::get(engine);
+
+        let data = match catch(Self::create) {
+            Ok(data) => data,
+            Err(error) => {
+                aux.error = error.message;
+                return error.code.into();
+            }
+        };
+        let queues = Queues {
+            outbox,
+            inbox,
+            engine_id: *addr_of!((*engine).engine_id),
+        };
+        let thread = thread::Builder::new()
+            .name("surena_engine".to_string())
+            .spawn(move || run(data, queues));
+        match thread {
+            Ok(thread) => {
+                aux.thread = Some(thread);
+                aux.inbox = inbox;
+            }
+            Err(e) => {
+                let error = Error::new_dynamic(
+                    ErrorCode::OutOfMemory,
+                    format!("failed to spawn engine thread: {e}"),
+                );
+                aux.error = error.message;
+                return error.code.into();
+            }
+        }
+
+        sys::ERR_ERR_OK
+    }
+
+    /// Sends an exit event to the engine thread and waits for it to finish.
+    #[doc(hidden)]
+    unsafe extern "C" fn destroy_wrapped(engine: *mut sys::engine) -> sys::error_code {
+        Aux::free(engine);
+
+        sys::ERR_ERR_OK
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn is_game_compatible_wrapped(
+        engine: *mut sys::engine,
+        game: *mut sys::game,
+    ) -> sys::error_code {
+        let methods = &**addr_of!((*game).methods);
+        if let Err(error) = catch(|| Self::is_game_compatible(methods)) {
+            Aux::get(engine).error = error.message;
+            return error.code.into();
+        }
+
+        sys::ERR_ERR_OK
+    }
+}
+
+/// Non-function members for [`engine_methods`].
+pub struct EngineMetadata {
+    pub engine_name: ValidCStr<'static>,
+    pub impl_name: ValidCStr<'static>,
+    pub version: semver,
+    pub features: engine_feature_flags,
+}
+
+/// Create _surena_ [`engine_methods`] from engine struct `E` and `metadata`.
+///
+/// # Example
+/// ```ignore
+/// create_engine_methods::<MyEngine>(metadata);
+/// ```
+pub fn create_engine_methods<E: EngineMethods>(metadata: EngineMetadata) -> engine_methods {
+    engine_methods {
+        engine_name: metadata.engine_name.into(),
+        impl_name: metadata.impl_name.into(),
+        version: metadata.version,
+        features: metadata.features,
+        get_last_error: Some(E::get_last_error_wrapped),
+        create: Some(E::create_wrapped),
+        destroy: Some(E::destroy_wrapped),
+        is_game_compatible: Some(E::is_game_compatible_wrapped),
+        ..Default::default()
+    }
+}
+
+/// Handle for sending events from the engine to the host.
+pub struct Outbox {
+    queue: *mut sys::eevent_queue,
+    engine_id: u32,
+}
+
+impl Outbox {
+    /// Reports `mov` as the best move for `player`.
+    pub fn best_move(&mut self, player: player_id, mov: move_code) {
+        self.push(|e, id| unsafe { sys::eevent_create_bestmove(e, id, player, mov) });
+    }
+
+    /// Sends `message` to the host log.
+    ///
+    /// NUL bytes are removed from `message`.
+    pub fn log(&mut self, message: &str) {
+        let message = CString::new(message.replace('\0', "")).expect("NUL bytes were removed");
+        self.push(|e, id| unsafe { sys::eevent_create_log_str(e, id, message.as_ptr()) });
+    }
+
+    fn log_error(&mut self, error: &ErrorString) {
+        let message: *const c_char = error.into();
+        if message.is_null() {
+            self.log("engine error without message");
+        } else {
+            self.push(|e, id| unsafe { sys::eevent_create_log_str(e, id, message) });
+        }
+    }
+
+    /// Creates an event using `create` and moves it into the queue.
+    fn push(&mut self, create: impl FnOnce(*mut sys::engine_event, u32)) {
+        let mut event = MaybeUninit::<sys::engine_event>::uninit();
+        create(event.as_mut_ptr(), self.engine_id);
+        // The queue takes ownership of the event.
+        unsafe { sys::eevent_queue_push(self.queue, event.as_mut_ptr()) };
+    }
+}
+
+/// Queues handed to the engine thread.
+struct Queues {
+    outbox: *mut sys::eevent_queue,
+    inbox: *mut sys::eevent_queue,
+    engine_id: u32,
+}
+
+// The surena event queues are thread-safe.
+unsafe impl Send for Queues {}
+
+/// Runs `body` and converts a panic into an error.
+fn catch<T>(body: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        Err(Error::new_dynamic(
+            ErrorCode::StateUnrecoverable,
+            format!("engine panicked: {}", panic_message(&*payload)),
+        ))
+    })
+}
+
+/// Event loop of the engine thread.
+fn run<E: EngineMethods>(mut engine: E, queues: Queues) {
+    let mut out = Outbox {
+        queue: queues.outbox,
+        engine_id: queues.engine_id,
+    };
+    let mut searching = false;
+
+    loop {
+        let mut event = MaybeUninit::<sys::engine_event>::uninit();
+        let timeout = if searching { 0 } else { POLL_TIMEOUT };
+        let mut event = unsafe {
+            sys::eevent_queue_pop(queues.inbox, event.as_mut_ptr(), timeout);
+            event.assume_init()
+        };
+
+        match event.type_ {
+            sys::EE_TYPE_EXIT => {
+                unsafe { sys::eevent_destroy(&mut event) };
+                break;
+            }
+            sys::EE_TYPE_HEARTBEAT => {
+                // Answer with the same event, the queue takes ownership.
+                unsafe { sys::eevent_queue_push(queues.outbox, &mut event) };
+                continue;
+            }
+            _ => {}
+        }
+
+        let result = catch_unwind(AssertUnwindSafe(|| match event.type_ {
+            sys::EE_TYPE_NULL if searching => engine.search_step(&mut out).map(|s| searching = s),
+            sys::EE_TYPE_GAME_LOAD => unsafe {
+                let load = event.__bindgen_anon_1.load;
+                searching = false;
+                engine.game_load(&*load.methods, &GameInit::new(&*load.init_info), &mut out)
+            },
+            sys::EE_TYPE_GAME_UNLOAD => {
+                searching = false;
+                engine.game_unload(&mut out)
+            }
+            sys::EE_TYPE_GAME_STATE => unsafe {
+                let state = cstr_to_rust(event.__bindgen_anon_1.state.state);
+                engine.game_state(state, &mut out)
+            },
+            sys::EE_TYPE_GAME_MOVE => unsafe {
+                let mov = event.__bindgen_anon_1.move_;
+                engine.game_move(mov.player, mov.code, &mut out)
+            },
+            sys::EE_TYPE_ENGINE_START => unsafe {
+                let start = event.__bindgen_anon_1.start;
+                let timeout =
+                    (start.timeout > 0).then(|| Duration::from_millis(start.timeout.into()));
+                let result = engine.search_start(start.player, timeout, &mut out);
+                searching = result.is_ok();
+                result
+            },
+            sys::EE_TYPE_ENGINE_STOP => {
+                searching = false;
+                engine.search_stop(&mut out)
+            }
+            // Ignore timeouts and unsupported events.
+            _ => Ok(()),
+        }));
+        unsafe { sys::eevent_destroy(&mut event) };
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => out.log_error(&error.message),
+            Err(payload) => {
+                // The engine might be inconsistent, so no more events are
+                // handled.
+                let error = Error::new_dynamic(
+                    ErrorCode::StateUnrecoverable,
+                    format!("engine panicked: {}", panic_message(&*payload)),
+                );
+                out.log_error(&error.message);
+                break;
+            }
+        }
+    }
+}
+
+struct Aux {
+    error: ErrorString,
+    thread: Option<JoinHandle<()>>,
+    /// Inbox of the engine thread for sending the exit event.
+    inbox: *mut sys::eevent_queue,
+}
+
+impl Aux {
+    unsafe fn init(engine: *mut sys::engine) {
+        // Initialize data2 to zero as it is unused.
+        addr_of_mut!((*engine).data2).write(null_mut());
+        let aux = Box::into_raw(Box::new(Self {
+            error: ErrorString::default(),
+            thread: None,
+            inbox: null_mut(),
+        }));
+        addr_of_mut!((*engine).data1).write(aux.cast());
+    }
+
+    #[inline]
+    unsafe fn get<'l>(engine: *mut sys::engine) -> &'l mut Self {
+        let data1: *mut *mut c_void = addr_of_mut!((*engine).data1);
+        &mut *(*data1).cast::<Self>()
+    }
+
+    unsafe fn free(engine: *mut sys::engine) {
+        let aux: &mut *mut c_void = &mut *addr_of_mut!((*engine).data1);
+        if !aux.is_null() {
+            let aux_box = Box::from_raw(aux.cast::<Self>());
+            // Leave as null pointer to catch use-after-free errors.
+            *aux = null_mut();
+            if let Some(thread) = aux_box.thread {
+                // The host may not have sent an exit event yet.
+                // A second one is ignored as the thread stops at the first.
+                let mut event = MaybeUninit::<sys::engine_event>::uninit();
+                let engine_id = *addr_of!((*engine).engine_id);
+                sys::eevent_create(event.as_mut_ptr(), engine_id, sys::EE_TYPE_EXIT);
+                sys::eevent_queue_push(aux_box.inbox, event.as_mut_ptr());
+                // Panics during event handling have already been logged by
+                // the engine thread.
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cstr, host::RawInit};
+
+    use std::{ffi::CStr, mem::zeroed, sync::Mutex};
+
+    /// Methods called on [`Recording`] engines.
+    static CALLS: Mutex<Vec<&str>> = Mutex::new(vec![]);
+
+    struct Recording;
+
+    impl Recording {
+        fn call(name: &'static str) {
+            CALLS.lock().unwrap().push(name);
+        }
+    }
+
+    impl EngineMethods for Recording {
+        fn create() -> Result<Self> {
+            Ok(Self)
+        }
+
+        fn is_game_compatible(_methods: &game_methods) -> Result<()> {
+            Ok(())
+        }
+
+        fn game_load(
+            &mut self,
+            _methods: &game_methods,
+            init_info: &GameInit,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            assert!(matches!(init_info, GameInit::Default));
+            Self::call("load");
+            Ok(())
+        }
+
+        fn game_state(&mut self, _state: Option<&str>, _out: &mut Outbox) -> Result<()> {
+            Self::call("state");
+            Ok(())
+        }
+
+        fn game_move(
+            &mut self,
+            _player: player_id,
+            _mov: move_code,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            Self::call("move");
+            Ok(())
+        }
+
+        fn search_start(
+            &mut self,
+            player: player_id,
+            _timeout: Option<Duration>,
+            out: &mut Outbox,
+        ) -> Result<()> {
+            Self::call("start");
+            out.best_move(player, 42);
+            Ok(())
+        }
+
+        fn search_stop(&mut self, _out: &mut Outbox) -> Result<()> {
+            Self::call("stop");
+            Ok(())
+        }
+    }
+
+    impl Drop for Recording {
+        fn drop(&mut self) {
+            Self::call("drop");
+        }
+    }
+
+    /// Panics when handling any game event.
+    struct Panicking;
+
+    impl EngineMethods for Panicking {
+        fn create() -> Result<Self> {
+            Ok(Self)
+        }
+
+        fn is_game_compatible(_methods: &game_methods) -> Result<()> {
+            Ok(())
+        }
+
+        fn game_load(
+            &mut self,
+            _methods: &game_methods,
+            _init_info: &GameInit,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            panic!("load")
+        }
+
+        fn game_state(&mut self, _state: Option<&str>, _out: &mut Outbox) -> Result<()> {
+            panic!("state")
+        }
+
+        fn game_move(
+            &mut self,
+            _player: player_id,
+            _mov: move_code,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            panic!("move")
+        }
+
+        fn search_start(
+            &mut self,
+            _player: player_id,
+            _timeout: Option<Duration>,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn search_stop(&mut self, _out: &mut Outbox) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Panics when being created or asked for compatibility.
+    struct Uncreatable;
+
+    impl EngineMethods for Uncreatable {
+        fn create() -> Result<Self> {
+            panic!("create")
+        }
+
+        fn is_game_compatible(_methods: &game_methods) -> Result<()> {
+            panic!("compatible")
+        }
+
+        fn game_load(
+            &mut self,
+            _methods: &game_methods,
+            _init_info: &GameInit,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            unreachable!()
+        }
+
+        fn game_state(&mut self, _state: Option<&str>, _out: &mut Outbox) -> Result<()> {
+            unreachable!()
+        }
+
+        fn game_move(
+            &mut self,
+            _player: player_id,
+            _mov: move_code,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            unreachable!()
+        }
+
+        fn search_start(
+            &mut self,
+            _player: player_id,
+            _timeout: Option<Duration>,
+            _out: &mut Outbox,
+        ) -> Result<()> {
+            unreachable!()
+        }
+
+        fn search_stop(&mut self, _out: &mut Outbox) -> Result<()> {
+            unreachable!()
+        }
+    }
+
+    fn methods<E: EngineMethods>() -> engine_methods {
+        create_engine_methods::<E>(EngineMetadata {
+            engine_name: cstr("Test\0"),
+            impl_name: cstr("surena_game_rs\0"),
+            version: semver {
+                major: 0,
+                minor: 1,
+                patch: 0,
+            },
+            features: engine_feature_flags::default(),
+        })
+    }
+
+    fn queue() -> Box<sys::eevent_queue> {
+        let mut queue = Box::new_uninit();
+        unsafe {
+            sys::eevent_queue_create(queue.as_mut_ptr());
+            queue.assume_init()
+        }
+    }
+
+    /// Pushes an event of type `type_` with data set by `data`.
+    fn send(
+        queue: &mut sys::eevent_queue,
+        type_: sys::eevent_type,
+        data: impl FnOnce(&mut sys::engine_event),
+    ) {
+        let mut event = MaybeUninit::<sys::engine_event>::uninit();
+        unsafe {
+            sys::eevent_create(event.as_mut_ptr(), 1, type_);
+            data(event.assume_init_mut());
+            sys::eevent_queue_push(queue, event.as_mut_ptr());
+        }
+    }
+
+    /// Pops the next event and returns its type.
+    fn receive(queue: &mut sys::eevent_queue) -> sys::eevent_type {
+        let mut event = MaybeUninit::<sys::engine_event>::uninit();
+        unsafe {
+            sys::eevent_queue_pop(queue, event.as_mut_ptr(), 0);
+            let mut event = event.assume_init();
+            let type_ = event.type_;
+            sys::eevent_destroy(&mut event);
+            type_
+        }
+    }
+
+    /// Creates an engine with events already waiting in its inbox and
+    /// destroys it.
+    fn run_engine(
+        methods: &engine_methods,
+        inbox: &mut sys::eevent_queue,
+        outbox: &mut sys::eevent_queue,
+    ) {
+        let mut engine = sys::engine {
+            methods,
+            engine_id: 1,
+            data1: null_mut(),
+            data2: null_mut(),
+        };
+        unsafe {
+            let code = methods.create.unwrap()(&mut engine, outbox, inbox);
+            assert_eq!(sys::ERR_ERR_OK, code);
+            assert_eq!(sys::ERR_ERR_OK, methods.destroy.unwrap()(&mut engine));
+        }
+    }
+
+    #[test]
+    fn lifecycle() {
+        let methods = methods::<Recording>();
+        let (mut inbox, mut outbox) = (queue(), queue());
+        // The test engine does not access the game methods.
+        let game: game_methods = unsafe { zeroed() };
+        let mut init_info = RawInit::new(&GameInit::Default).unwrap();
+
+        send(&mut inbox, sys::EE_TYPE_GAME_LOAD, |e| {
+            e.__bindgen_anon_1.load = sys::ee_game_load {
+                methods: &game,
+                init_info: &mut init_info.raw,
+            }
+        });
+        send(&mut inbox, sys::EE_TYPE_ENGINE_START, |e| {
+            e.__bindgen_anon_1.start = sys::ee_engine_start {
+                player: 1,
+                timeout: 0,
+                ponder: false,
+            }
+        });
+        send(&mut inbox, sys::EE_TYPE_ENGINE_STOP, |_| {});
+        send(&mut inbox, sys::EE_TYPE_EXIT, |_| {});
+        run_engine(&methods, &mut inbox, &mut outbox);
+        assert_eq!(*CALLS.lock().unwrap(), ["load", "start", "stop", "drop"]);
+
+        assert_eq!(sys::EE_TYPE_ENGINE_BESTMOVE, receive(&mut outbox));
+
+        // Destroying the engine without an exit event must not block.
+        CALLS.lock().unwrap().clear();
+        run_engine(&methods, &mut inbox, &mut outbox);
+        assert_eq!(*CALLS.lock().unwrap(), ["drop"]);
+
+        unsafe {
+            sys::eevent_queue_destroy(&mut *inbox);
+            sys::eevent_queue_destroy(&mut *outbox);
+        }
+    }
+
+    #[test]
+    fn panic_stops_engine() {
+        let methods = methods::<Panicking>();
+        let (mut inbox, mut outbox) = (queue(), queue());
+        send(&mut inbox, sys::EE_TYPE_GAME_STATE, |e| {
+            e.__bindgen_anon_1.state = sys::ee_game_state { state: null_mut() }
+        });
+        send(&mut inbox, sys::EE_TYPE_GAME_MOVE, |e| {
+            e.__bindgen_anon_1.move_ = sys::ee_game_move { player: 1, code: 0 }
+        });
+        run_engine(&methods, &mut inbox, &mut outbox);
+
+        // Only the first panic is logged.
+        assert_eq!(sys::EE_TYPE_LOG, receive(&mut outbox));
+        assert_eq!(sys::EE_TYPE_NULL, receive(&mut outbox));
+
+        unsafe {
+            sys::eevent_queue_destroy(&mut *inbox);
+            sys::eevent_queue_destroy(&mut *outbox);
+        }
+    }
+
+    #[test]
+    fn panics_outside_event_loop_are_reported() {
+        let methods = methods::<Uncreatable>();
+        let (mut inbox, mut outbox) = (queue(), queue());
+        let mut engine = sys::engine {
+            methods: &methods,
+            engine_id: 1,
+            data1: null_mut(),
+            data2: null_mut(),
+        };
+        // The engine does not access the game methods.
+        let game_methods: game_methods = unsafe { zeroed() };
+        let mut game: sys::game = unsafe { zeroed() };
+        game.methods = &game_methods;
+        let expected: sys::error_code = ErrorCode::StateUnrecoverable.into();
+
+        unsafe {
+            let last_error = |engine: &mut sys::engine| {
+                CStr::from_ptr(methods.get_last_error.unwrap()(engine))
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            let code = methods.create.unwrap()(&mut engine, &mut *outbox, &mut *inbox);
+            assert_eq!(expected, code);
+            assert_eq!("engine panicked: create", last_error(&mut engine));
+            let code = methods.is_game_compatible.unwrap()(&mut engine, &mut game);
+            assert_eq!(expected, code);
+            assert_eq!("engine panicked: compatible", last_error(&mut engine));
+            assert_eq!(sys::ERR_ERR_OK, methods.destroy.unwrap()(&mut engine));
+
+            sys::eevent_queue_destroy(&mut *inbox);
+            sys::eevent_queue_destroy(&mut *outbox);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Zobrist hashing for implementing
+//! [`GameMethods::get_id()`](crate::GameMethods::get_id).
+//!
+//! A state is described by a set of features (e.g., "white knight on b1").
+//! Each feature is assigned a random key and the hash of a state is the XOR of
+//! the keys of all present features.
+//! Therefore, the hash can be updated incrementally in
+//! [`GameMethods::make_move()`](crate::GameMethods::make_move) by toggling the
+//! features which changed.
+
+use crate::rng::Rng;
+
+use std::sync::Arc;
+
+/// Table of random keys used for Zobrist hashing.
+///
+/// The keys are generated deterministically from the seed.
+/// Thus, equal states always have equal ids, even across game instances and
+/// program runs.
+/// Cloning the table is cheap because the keys are shared.
+///
+/// # Example
+/// ```
+/// # use surena_game::hash::{Zobrist, ZobristHash};
+/// const SQUARES: usize = 9;
+/// const PIECES: usize = 2;
+///
+/// let table = Zobrist::new(SQUARES * PIECES, 0);
+/// let mut hash = ZobristHash::default();
+/// // Place piece 1 on square 4 and remove it again.
+/// hash.toggle(&table, 4 * PIECES + 1);
+/// assert_ne!(0, hash.get());
+/// hash.toggle(&table, 4 * PIECES + 1);
+/// assert_eq!(0, hash.get());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Zobrist {
+    keys: Arc<[u64]>,
+}
+
+impl Zobrist {
+    /// Create a table with `size` many keys derived from `seed`.
+    pub fn new(size: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        Self {
+            keys: (0..size).map(|_| rng.next_u64()).collect(),
+        }
+    }
+
+    /// Returns the key of the feature at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn key(&self, index: usize) -> u64 {
+        self.keys[index]
+    }
+
+    /// Number of keys in this table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether the table contains no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// An incrementally updatable Zobrist hash.
+///
+/// The empty state has the hash `0`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ZobristHash(u64);
+
+impl ZobristHash {
+    /// Adds or removes the feature at `index` of `table`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn toggle(&mut self, table: &Zobrist, index: usize) {
+        self.0 ^= table.key(index);
+    }
+
+    /// Returns the hash value which can be used as the state id.
+    #[inline]
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
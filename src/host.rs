@@ -0,0 +1,715 @@
+//! Safe API for driving _surena_ games from Rust.
+//!
+//! A [`Game`] can be created from any [`game_methods`], e.g., those generated
+//! by [`create_game_methods()`](crate::create_game_methods) or those exported
+//! by a game plugin loaded via `Plugin` (requires the `dlopen` feature).
+//! All buffers are owned by the [`Game`] and sized according to its
+//! [`buf_sizer`].
+
+use crate::{
+    buf_sizer, game_methods, move_code, player_id, ptr_vec::Storage, sys, Error, ErrorCode,
+    GameInit, Result,
+};
+
+use std::{
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    mem::zeroed,
+    os::raw::c_char,
+    ptr::null,
+    str::from_utf8,
+};
+
+/// An instance of a _surena_ game.
+///
+/// The game is destroyed when this is dropped or via [`Game::destroy`].
+///
+/// # Example
+/// ```ignore
+/// let methods = create_game_methods::<MyGame>(metadata);
+/// let mut game = Game::new(&methods, &GameInit::Default)?;
+/// let player = game.players_to_move()?[0];
+/// let mov = game.get_concrete_moves(player)?[0];
+/// game.make_move(player, mov)?;
+/// println!("{}", game.export_state()?);
+/// ```
+pub struct Game<'m> {
+    raw: Box<sys::game>,
+    methods: &'m game_methods,
+    str_buf: Storage<u8>,
+    players: Storage<player_id>,
+    moves: Storage<move_code>,
+    destroyed: bool,
+}
+
+impl<'m> Game<'m> {
+    /// Create a new game using `methods` and `init_info`.
+    pub fn new(methods: &'m game_methods, init_info: &GameInit) -> Result<Self> {
+        let create = method(methods.create, "create")?;
+        let mut init_info = RawInit::new(init_info)?;
+
+        // Dropping the game on failure destroys it as required by surena.
+        let mut game = Self::empty(methods);
+        let code = unsafe { create(game.raw_mut(), &mut init_info.raw) };
+        game.check(code)?;
+        game.allocate_buffers();
+        Ok(game)
+    }
+
+    /// Create an uninitialized game which can be passed to `create` or
+    /// `clone`.
+    fn empty(methods: &'m game_methods) -> Self {
+        // The game struct consists of pointers and integers only.
+        let mut raw: Box<sys::game> = Box::new(unsafe { zeroed() });
+        raw.methods = methods;
+        Self {
+            raw,
+            methods,
+            str_buf: Storage::new(0),
+            players: Storage::new(0),
+            moves: Storage::new(0),
+            destroyed: false,
+        }
+    }
+
+    fn allocate_buffers(&mut self) {
+        let sizer = self.raw.sizer;
+        let str_len = [
+            sizer.options_str,
+            sizer.state_str,
+            sizer.legacy_str,
+            sizer.move_str,
+            sizer.print_str,
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or_default();
+        let players = sizer
+            .max_players_to_move
+            .max(sizer.max_results)
+            .max(sizer.player_count);
+        let moves = sizer.max_moves.max(sizer.max_actions);
+
+        self.str_buf = Storage::new(str_len);
+        self.players = Storage::new(players.into());
+        self.moves = Storage::new(moves as usize);
+    }
+
+    /// The [`game_methods`] this game was created from.
+    #[inline]
+    pub fn methods(&self) -> &'m game_methods {
+        self.methods
+    }
+
+    /// The [`buf_sizer`] reported by the game.
+    #[inline]
+    pub fn sizer(&self) -> &buf_sizer {
+        &self.raw.sizer
+    }
+
+    /// Create an independent copy of this game.
+    pub fn try_clone(&mut self) -> Result<Game<'m>> {
+        let clone = method(self.methods.clone, "clone")?;
+        let mut target = Self::empty(self.methods);
+        let code = unsafe { clone(self.raw_mut(), target.raw_mut()) };
+        self.check(code)?;
+        target.allocate_buffers();
+        Ok(target)
+    }
+
+    pub fn copy_from(&mut self, other: &mut Game) -> Result<()> {
+        let copy_from = method(self.methods.copy_from, "copy_from")?;
+        let code = unsafe { copy_from(self.raw_mut(), other.raw_mut()) };
+        self.check(code)
+    }
+
+    pub fn compare(&mut self, other: &mut Game) -> Result<bool> {
+        let compare = method(self.methods.compare, "compare")?;
+        let mut equal = false;
+        let code = unsafe { compare(self.raw_mut(), other.raw_mut(), &mut equal) };
+        self.check(code)?;
+        Ok(equal)
+    }
+
+    pub fn export_options(&mut self) -> Result<&str> {
+        let export_options = method(self.methods.export_options, "export_options")?;
+        self.read_str(|game, size, buf| unsafe { export_options(game, size, buf) })
+    }
+
+    pub fn import_state(&mut self, state: Option<&str>) -> Result<()> {
+        let import_state = method(self.methods.import_state, "import_state")?;
+        let state = state.map(to_cstring).transpose()?;
+        let code = unsafe {
+            import_state(
+                self.raw_mut(),
+                state.as_ref().map_or(null(), |s| s.as_ptr()),
+            )
+        };
+        self.check(code)
+    }
+
+    pub fn export_state(&mut self) -> Result<&str> {
+        let export_state = method(self.methods.export_state, "export_state")?;
+        self.read_str(|game, size, buf| unsafe { export_state(game, size, buf) })
+    }
+
+    pub fn players_to_move(&mut self) -> Result<&[player_id]> {
+        let players_to_move = method(self.methods.players_to_move, "players_to_move")?;
+        let mut count = 0;
+        let code =
+            unsafe { players_to_move(self.raw_mut(), &mut count, self.players.get_raw_ptr()) };
+        self.check(code)?;
+        written(&mut self.players, count.into())
+    }
+
+    pub fn get_concrete_moves(&mut self, player: player_id) -> Result<&[move_code]> {
+        let get_concrete_moves = method(self.methods.get_concrete_moves, "get_concrete_moves")?;
+        let mut count = 0;
+        let code = unsafe {
+            get_concrete_moves(self.raw_mut(), player, &mut count, self.moves.get_raw_ptr())
+        };
+        self.check(code)?;
+        written(&mut self.moves, count as usize)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn is_legal_move(&mut self, player: player_id, mov: move_code) -> Result<()> {
+        let is_legal_move = method(self.methods.is_legal_move, "is_legal_move")?;
+        let code = unsafe { is_legal_move(self.raw_mut(), player, mov) };
+        self.check(code)
+    }
+
+    pub fn make_move(&mut self, player: player_id, mov: move_code) -> Result<()> {
+        let make_move = method(self.methods.make_move, "make_move")?;
+        let code = unsafe { make_move(self.raw_mut(), player, mov) };
+        self.check(code)
+    }
+
+    /// Requires the `random_moves` feature of the game.
+    pub fn get_random_move(&mut self, seed: u64) -> Result<move_code> {
+        let get_random_move = method(self.methods.get_random_move, "get_random_move")?;
+        let mut mov = 0;
+        let code = unsafe { get_random_move(self.raw_mut(), seed, &mut mov) };
+        self.check(code)?;
+        Ok(mov)
+    }
+
+    pub fn get_results(&mut self) -> Result<&[player_id]> {
+        let get_results = method(self.methods.get_results, "get_results")?;
+        let mut count = 0;
+        let code = unsafe { get_results(self.raw_mut(), &mut count, self.players.get_raw_ptr()) };
+        self.check(code)?;
+        written(&mut self.players, count.into())
+    }
+
+    pub fn get_move_code(&mut self, player: player_id, string: &str) -> Result<move_code> {
+        let get_move_code = method(self.methods.get_move_code, "get_move_code")?;
+        let string = to_cstring(string)?;
+        let mut mov = 0;
+        let code = unsafe { get_move_code(self.raw_mut(), player, string.as_ptr(), &mut mov) };
+        self.check(code)?;
+        Ok(mov)
+    }
+
+    pub fn get_move_str(&mut self, player: player_id, mov: move_code) -> Result<&str> {
+        let get_move_str = method(self.methods.get_move_str, "get_move_str")?;
+        self.read_str(|game, size, buf| unsafe { get_move_str(game, player, mov, size, buf) })
+    }
+
+    pub fn print(&mut self) -> Result<&str> {
+        let print = method(self.methods.print, "print")?;
+        self.read_str(|game, size, buf| unsafe { print(game, size, buf) })
+    }
+
+    /// Returns the message of the last error.
+    pub fn get_last_error(&mut self) -> Option<String> {
+        let get_last_error = self.methods.get_last_error?;
+        let message = unsafe { get_last_error(self.raw_mut()) };
+        if message.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    /// Calls `f` with a string buffer and returns the written string.
+    fn read_str(
+        &mut self,
+        f: impl FnOnce(*mut sys::game, *mut usize, *mut c_char) -> sys::error_code,
+    ) -> Result<&str> {
+        let mut size = 0;
+        let code = f(self.raw_mut(), &mut size, self.str_buf.get_raw_ptr().cast());
+        self.check(code)?;
+        from_utf8(written(&mut self.str_buf, size)?).map_err(|e| {
+            Error::new_dynamic(
+                ErrorCode::StateCorrupted,
+                format!("game returned invalid UTF-8: {e}"),
+            )
+        })
+    }
+
+    /// Converts the returned `code` into an [`Error`] with the message from
+    /// `get_last_error`.
+    fn check(&mut self, code: sys::error_code) -> Result<()> {
+        if code == sys::ERR_ERR_OK {
+            return Ok(());
+        }
+        let message = self
+            .get_last_error()
+            .unwrap_or_else(|| format!("game returned error code {code}"));
+        Err(Error::new_dynamic(code.into(), message))
+    }
+
+    /// Destroy the game like dropping it does, but report errors.
+    ///
+    /// The game data is gone afterwards, so the error has no message from
+    /// the game.
+    pub fn destroy(mut self) -> Result<()> {
+        let code = self.destroy_raw();
+        if code == sys::ERR_ERR_OK {
+            return Ok(());
+        }
+        Err(Error::new_dynamic(
+            code.into(),
+            format!("game returned error code {code} while being destroyed"),
+        ))
+    }
+
+    /// Calls `destroy` unless it has already been called.
+    fn destroy_raw(&mut self) -> sys::error_code {
+        match self.methods.destroy {
+            Some(destroy) if !self.destroyed => {
+                self.destroyed = true;
+                unsafe { destroy(self.raw_mut()) }
+            }
+            _ => sys::ERR_ERR_OK,
+        }
+    }
+
+    #[inline]
+    fn raw_mut(&mut self) -> *mut sys::game {
+        &mut *self.raw
+    }
+}
+
+impl Drop for Game<'_> {
+    fn drop(&mut self) {
+        // Errors cannot be reported during drop.
+        self.destroy_raw();
+    }
+}
+
+/// A [`sys::game_init`] together with the memory it points to.
+pub(crate) struct RawInit<'l> {
+    pub(crate) raw: sys::game_init,
+    _strings: [Option<CString>; 3],
+    _bytes: PhantomData<&'l [u8]>,
+}
+
+impl<'l> RawInit<'l> {
+    pub(crate) fn new(init_info: &GameInit<'l>) -> Result<Self> {
+        let mut strings = [None, None, None];
+        // The union is fully initialized in every branch.
+        let mut raw: sys::game_init = unsafe { zeroed() };
+        match init_info {
+            GameInit::Default => {
+                raw.source_type = sys::GAME_INIT_SOURCE_TYPE_GAME_INIT_SOURCE_TYPE_DEFAULT;
+            }
+            GameInit::Standard {
+                opts,
+                legacy,
+                state,
+            } => {
+                for (string, value) in strings.iter_mut().zip([opts, legacy, state]) {
+                    *string = value.map(to_cstring).transpose()?;
+                }
+                let [opts, legacy, state] = strings
+                    .each_ref()
+                    .map(|s| s.as_ref().map_or(null(), |s| s.as_ptr()));
+                raw.source_type = sys::GAME_INIT_SOURCE_TYPE_GAME_INIT_SOURCE_TYPE_STANDARD;
+                raw.source.standard = sys::game_init_standard {
+                    opts,
+                    legacy,
+                    state,
+                };
+            }
+            GameInit::Serialized(bytes) => {
+                let range = bytes.as_ptr_range();
+                raw.source_type = sys::GAME_INIT_SOURCE_TYPE_GAME_INIT_SOURCE_TYPE_SERIALIZED;
+                raw.source.serialized = sys::game_init_serialized {
+                    buf_begin: range.start.cast(),
+                    buf_end: range.end.cast(),
+                };
+            }
+        }
+
+        Ok(Self {
+            raw,
+            _strings: strings,
+            _bytes: PhantomData,
+        })
+    }
+}
+
+/// A game plugin loaded from a shared library.
+///
+/// The plugin is cleaned up and unloaded when this is dropped.
+/// Hence, [`Game`]s borrow the [`game_methods`] from the [`Plugin`].
+#[cfg(feature = "dlopen")]
+pub struct Plugin {
+    library: libloading::Library,
+    methods: Vec<*const game_methods>,
+}
+
+#[cfg(feature = "dlopen")]
+impl Plugin {
+    /// Loads the game plugin at `path`.
+    ///
+    /// The API version of the plugin must match
+    /// [`sys::SURENA_GAME_API_VERSION`].
+    ///
+    /// # Safety
+    /// Loading a library executes its initialization code.
+    /// The plugin must implement the _surena_ game API correctly.
+    pub unsafe fn load(path: impl AsRef<std::ffi::OsStr>) -> Result<Self> {
+        let library = libloading::Library::new(path).map_err(load_error)?;
+
+        let version: libloading::Symbol<extern "C" fn() -> u64> = library
+            .get(b"plugin_get_game_capi_version\0")
+            .map_err(load_error)?;
+        let version = version();
+        if version != sys::SURENA_GAME_API_VERSION {
+            return Err(Error::new_dynamic(
+                ErrorCode::FeatureUnsupported,
+                format!(
+                    "plugin has game API version {version} but {} is required",
+                    sys::SURENA_GAME_API_VERSION
+                ),
+            ));
+        }
+
+        let init: libloading::Symbol<unsafe extern "C" fn()> =
+            library.get(b"plugin_init_game\0").map_err(load_error)?;
+        init();
+
+        let get_methods: libloading::Symbol<
+            unsafe extern "C" fn(*mut u32, *mut *const game_methods),
+        > = match library.get(b"plugin_get_game_methods\0") {
+            Ok(get_methods) => get_methods,
+            Err(error) => {
+                // The plugin is initialized, so it must be cleaned up.
+                cleanup(&library);
+                return Err(load_error(error));
+            }
+        };
+        let mut count = 0;
+        get_methods(&mut count, std::ptr::null_mut());
+        let mut methods = vec![null(); count as usize];
+        get_methods(&mut count, methods.as_mut_ptr());
+        methods.truncate(count as usize);
+
+        Ok(Self { library, methods })
+    }
+
+    /// All [`game_methods`] exported by this plugin.
+    pub fn methods(&self) -> impl Iterator<Item = &game_methods> {
+        // The methods stay valid until the plugin is cleaned up.
+        self.methods.iter().map(|m| unsafe { &**m })
+    }
+}
+
+#[cfg(feature = "dlopen")]
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe { cleanup(&self.library) };
+    }
+}
+
+/// Calls `plugin_cleanup_game` if the plugin exports it.
+#[cfg(feature = "dlopen")]
+unsafe fn cleanup(library: &libloading::Library) {
+    let cleanup: std::result::Result<libloading::Symbol<unsafe extern "C" fn()>, _> =
+        library.get(b"plugin_cleanup_game\0");
+    if let Ok(cleanup) = cleanup {
+        cleanup();
+    }
+}
+
+#[cfg(feature = "dlopen")]
+fn load_error(error: libloading::Error) -> Error {
+    Error::new_dynamic(
+        ErrorCode::InvalidInput,
+        format!("failed to load plugin: {error}"),
+    )
+}
+
+/// Returns the function pointer or an error if the game does not provide it.
+fn method<F>(f: Option<F>, name: &str) -> Result<F> {
+    f.ok_or_else(|| {
+        Error::new_dynamic(
+            ErrorCode::FeatureUnsupported,
+            format!("game does not provide {name}"),
+        )
+    })
+}
+
+fn to_cstring(string: &str) -> Result<CString> {
+    CString::new(string)
+        .map_err(|_| Error::new_static(ErrorCode::InvalidInput, "string contains NUL byte\0"))
+}
+
+/// Marks the first `count` items of `storage` as written by the game.
+fn written<T: Copy>(storage: &mut Storage<T>, count: usize) -> Result<&[T]> {
+    if count > storage.capacity() {
+        return Err(Error::new_static(
+            ErrorCode::StateCorrupted,
+            "game wrote more than its buf_sizer allows\0",
+        ));
+    }
+    // Plain data written by the game is always initialized.
+    unsafe { storage.set_len(count) };
+    Ok(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_game_methods, cstr, describe, semver,
+        simple::{Limits, Simple, SimpleGame},
+        Metadata,
+    };
+
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Number of dropped [`Countdown`]s on this thread.
+        static DROPS: Cell<usize> = const { Cell::new(0) };
+        /// Makes the next [`Countdown`] dropped on this thread panic.
+        static PANIC_ON_DROP: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// The only player counts down by one or two until zero is reached.
+    ///
+    /// Counting below zero panics.
+    #[derive(Clone, PartialEq, Eq)]
+    struct Countdown(u8);
+
+    impl Drop for Countdown {
+        fn drop(&mut self) {
+            DROPS.with(|d| d.set(d.get() + 1));
+            if PANIC_ON_DROP.with(|p| p.replace(false)) {
+                panic!("drop failed");
+            }
+        }
+    }
+
+    impl SimpleGame for Countdown {
+        fn create(init_info: &GameInit) -> Result<Self> {
+            let mut game = Self(5);
+            if let GameInit::Standard { state, .. } = init_info {
+                game.import_state(*state)?;
+            }
+            Ok(game)
+        }
+
+        fn limits(&self) -> Limits {
+            Limits {
+                player_count: 1,
+                max_players_to_move: 1,
+                max_moves: 2,
+                max_results: 1,
+                state_str: 2,
+                move_str: 1,
+                ..Default::default()
+            }
+        }
+
+        fn import_state(&mut self, state: Option<&str>) -> Result<()> {
+            match state.unwrap_or("5").parse() {
+                Ok(counter @ 0..=99) => {
+                    self.0 = counter;
+                    Ok(())
+                }
+                _ => Err(Error::new_static(
+                    ErrorCode::InvalidState,
+                    "invalid state\0",
+                )),
+            }
+        }
+
+        fn export_state(&self) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn players_to_move(&self) -> Result<Vec<player_id>> {
+            Ok(if self.0 > 0 { vec![1] } else { vec![] })
+        }
+
+        fn get_concrete_moves(&self, _player: player_id) -> Result<Vec<move_code>> {
+            Ok((1..=self.0.min(2)).map(Into::into).collect())
+        }
+
+        fn get_move_str(&self, _player: player_id, mov: move_code) -> Result<String> {
+            Ok(mov.to_string())
+        }
+
+        fn make_move(&mut self, _player: player_id, mov: move_code) -> Result<()> {
+            self.0 = self.0.checked_sub(mov as u8).expect("below zero");
+            Ok(())
+        }
+
+        fn get_results(&self) -> Result<Vec<player_id>> {
+            Ok(if self.0 == 0 { vec![1] } else { vec![] })
+        }
+    }
+
+    fn methods() -> game_methods {
+        create_game_methods::<Simple<Countdown>>(Metadata {
+            game_name: cstr("Countdown\0"),
+            variant_name: cstr("Standard\0"),
+            impl_name: cstr("surena_game_rs\0"),
+            version: semver {
+                major: 0,
+                minor: 1,
+                patch: 0,
+            },
+            features: Default::default(),
+        })
+    }
+
+    #[test]
+    fn create_and_play() {
+        let methods = methods();
+        let mut game = Game::new(&methods, &GameInit::Default).unwrap();
+        assert_eq!(3, game.sizer().state_str);
+        assert_eq!("5", game.export_state().unwrap());
+        assert_eq!([1], game.players_to_move().unwrap());
+        assert_eq!([1, 2], game.get_concrete_moves(1).unwrap());
+
+        let mov = game.get_move_code(1, "2").unwrap();
+        assert_eq!("2", game.get_move_str(1, mov).unwrap());
+        game.is_legal_move(1, mov).unwrap();
+        game.make_move(1, mov).unwrap();
+        assert_eq!("3", game.export_state().unwrap());
+
+        game.import_state(Some("0")).unwrap();
+        assert!(game.players_to_move().unwrap().is_empty());
+        assert_eq!([1], game.get_results().unwrap());
+    }
+
+    #[test]
+    fn errors_are_propagated() {
+        let methods = methods();
+        let init_info = GameInit::Standard {
+            opts: None,
+            legacy: None,
+            state: Some("x"),
+        };
+        let Err(error) = Game::new(&methods, &init_info) else {
+            panic!("invalid state was accepted");
+        };
+        assert_eq!("invalid state", describe(&error));
+        assert_code(ErrorCode::InvalidState, &error);
+
+        let mut game = Game::new(&methods, &GameInit::Default).unwrap();
+        let error = game.is_legal_move(1, 3).unwrap_err();
+        assert_eq!("illegal move", describe(&error));
+        assert_eq!(Some("illegal move"), game.get_last_error().as_deref());
+        let error = game.import_state(Some("100")).unwrap_err();
+        assert_eq!("invalid state", describe(&error));
+        // Methods of disabled features are not provided.
+        let error = game.print().unwrap_err();
+        assert_eq!("game does not provide print", describe(&error));
+    }
+
+    #[test]
+    fn clone_and_copy() {
+        let methods = methods();
+        let mut game = Game::new(&methods, &GameInit::Default).unwrap();
+        let mut clone = game.try_clone().unwrap();
+        assert!(game.compare(&mut clone).unwrap());
+
+        clone.make_move(1, 2).unwrap();
+        assert!(!game.compare(&mut clone).unwrap());
+        assert_eq!("5", game.export_state().unwrap());
+
+        game.copy_from(&mut clone).unwrap();
+        assert!(game.compare(&mut clone).unwrap());
+        assert_eq!("3", game.export_state().unwrap());
+    }
+
+    #[test]
+    fn drop_destroys_game() {
+        let drops = || DROPS.with(Cell::get);
+        let methods = methods();
+        let mut game = Game::new(&methods, &GameInit::Default).unwrap();
+        let clone = game.try_clone().unwrap();
+        let before = drops();
+        drop(game);
+        assert_eq!(before + 1, drops());
+        drop(clone);
+        assert_eq!(before + 2, drops());
+    }
+
+    fn assert_code(expected: ErrorCode, error: &Error) {
+        let expected: sys::error_code = expected.into();
+        let code: sys::error_code = error.code.into();
+        assert_eq!(expected, code);
+    }
+
+    #[test]
+    fn panics_poison_the_game() {
+        let methods = methods();
+        let mut game = Game::new(&methods, &GameInit::Default).unwrap();
+        let mut healthy = game.try_clone().unwrap();
+
+        let error = game.make_move(1, 6).unwrap_err();
+        assert_code(ErrorCode::StateUnrecoverable, &error);
+        assert_eq!("game panicked: below zero", describe(&error));
+        assert_eq!(
+            Some("game panicked: below zero"),
+            game.get_last_error().as_deref()
+        );
+
+        // All later calls fail instead of using the inconsistent state.
+        let errors = [
+            game.make_move(1, 1).unwrap_err(),
+            game.export_state().unwrap_err(),
+            game.players_to_move().unwrap_err(),
+            game.try_clone().err().expect("poisoned game was cloned"),
+            game.copy_from(&mut healthy).unwrap_err(),
+        ];
+        for error in &errors {
+            assert_code(ErrorCode::StateUnrecoverable, error);
+            assert!(describe(error).contains("poisoned"), "{}", describe(error));
+        }
+
+        // The poisoned game must not be read by healthy ones either.
+        let errors = [
+            healthy.copy_from(&mut game).unwrap_err(),
+            healthy.compare(&mut game).unwrap_err(),
+        ];
+        for error in &errors {
+            assert_code(ErrorCode::StateUnrecoverable, error);
+            assert!(describe(error).contains("poisoned"), "{}", describe(error));
+        }
+        assert_eq!("5", healthy.export_state().unwrap());
+    }
+
+    #[test]
+    fn panicking_drop_is_reported() {
+        let drops = || DROPS.with(Cell::get);
+        let methods = methods();
+        let game = Game::new(&methods, &GameInit::Default).unwrap();
+        let before = drops();
+        PANIC_ON_DROP.with(|p| p.set(true));
+        let error = game.destroy().unwrap_err();
+        assert_code(ErrorCode::StateUnrecoverable, &error);
+        // The game data was dropped nonetheless.
+        assert_eq!(before + 1, drops());
+    }
+}
@@ -1,15 +1,29 @@
 //! This is a wrapper library for the game API of the
 //! [_surena_](https://github.com/RememberOfLife/surena/) game engine.
 
+pub mod ai;
+pub mod board;
+pub mod codec;
+pub mod engine;
+pub mod hash;
+pub mod host;
+pub mod options;
+pub mod playout;
 pub mod ptr_vec;
+pub mod record;
+pub mod rng;
+pub mod serialize;
+pub mod simple;
+pub mod sizer;
+pub mod testing;
 
 pub use mirabel_sys::{
     self, count, cstr,
     error::{CustomCode, Error, ErrorCode, ErrorString, Result},
     game_init::GameInit,
     sys::{
-        self, buf_sizer, game_feature_flags, game_methods, move_code, player_id, semver, MOVE_NONE,
-        PLAYER_NONE, PLAYER_RAND,
+        self, buf_sizer, game_feature_flags, game_methods, move_code, player_id, semver,
+        sync_counter, MOVE_NONE, PLAYER_NONE, PLAYER_RAND, SYNC_COUNTER_DEFAULT,
     },
     ValidCStr,
 };
@@ -17,10 +31,13 @@ use mirabel_sys::{cstr_to_rust, cstr_to_rust_unchecked};
 pub use ptr_vec::PtrVec;
 
 use std::{
-    ffi::c_void,
+    any::Any,
+    ffi::{c_void, CStr},
     num::NonZeroU8,
     os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
     ptr::{addr_of, addr_of_mut, null_mut},
+    slice,
 };
 
 /// This macro creates the `plugin_get_game_methods` function.
@@ -100,6 +117,10 @@ pub type StrBuf<'b> = PtrVec<'b, NonZeroU8>;
 /// See `game.h` @ _surena_ for API documentation.
 /// You should **not implement `[...]_wrapped`** methods.
 ///
+/// Panics are caught at the FFI boundary and reported as
+/// [`ErrorCode::StateUnrecoverable`].
+/// Afterwards, the game instance is poisoned and all further calls fail.
+///
 /// Games need to implement [`Drop`] for custom `destroy` handling.
 /// `clone` is handled by the [`Clone`] implementation and `compare` by [`Eq`].
 /// The [`Send`] bound is required by the surena API.
@@ -107,6 +128,16 @@ pub type StrBuf<'b> = PtrVec<'b, NonZeroU8>;
 /// # Example
 /// See the `./example` crate in the project root.
 pub trait GameMethods: Sized + Clone + Eq + Send {
+    /// Whether [`Self::get_actions`], [`Self::is_action`], and
+    /// [`Self::move_to_action`] are exported.
+    ///
+    /// Actions are independent of the feature flags, as they are also useful
+    /// for games with perfect information but huge or continuous move spaces.
+    const ACTIONS: bool = false;
+
+    /// With [`game_feature_flags::legacy`], the `legacy` of
+    /// [`GameInit::Standard`] contains the string written by
+    /// [`Self::export_legacy`] in a previous match.
     fn create(init_info: &GameInit) -> Result<(Self, buf_sizer)>;
     fn copy_from(&mut self, other: &mut Self) -> Result<()>;
     fn import_state(&mut self, string: Option<&str>) -> Result<()>;
@@ -124,11 +155,29 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         mov: move_code,
         str_buf: &mut StrBuf,
     ) -> Result<()>;
-    fn make_move(&mut self, player: player_id, mov: move_code) -> Result<()>;
+    /// `sync_ctr` is the sync counter of this game instance.
+    ///
+    /// With [`game_feature_flags::simultaneous_moves`], it should be
+    /// incremented whenever the moves of all players are synchronized (e.g.,
+    /// when simultaneous moves are revealed).
+    fn make_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()>;
     fn get_results(&mut self, players: &mut PtrVec<player_id>) -> Result<()>;
-    /// Sync counters are currently not supported.
+    /// `sync_ctr` is the current sync counter of this game instance (see
+    /// [`Self::make_move`]), not a counter supplied with `mov`.
+    /// The C API does not pass the sync counter of a move, so stale moves
+    /// cannot be detected by comparing it.
     #[allow(clippy::wrong_self_convention)]
-    fn is_legal_move(&mut self, player: player_id, mov: move_code) -> Result<()>;
+    fn is_legal_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: sync_counter,
+    ) -> Result<()>;
 
     /// Must be implemented when the [`game_feature_flags::options`] is enabled.
     #[allow(unused_variables)]
@@ -140,6 +189,172 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
     fn print(&mut self, str_buf: &mut StrBuf) -> Result<()> {
         unimplemented!("print")
     }
+    /// Must be implemented when the [`game_feature_flags::serializable`] is
+    /// enabled.
+    ///
+    /// The written data must be accepted by [`Self::create`] as
+    /// [`GameInit::Serialized`].
+    /// The written length must not exceed [`buf_sizer::serialize`].
+    /// See the [`serialize`](mod@serialize) module for helpers.
+    #[allow(unused_variables)]
+    fn serialize(&mut self, buf: &mut PtrVec<u8>) -> Result<()> {
+        unimplemented!("serialize")
+    }
+    /// Must be implemented when the [`game_feature_flags::random_moves`] is
+    /// enabled.
+    ///
+    /// Writes the probability of each move returned by
+    /// [`Self::get_concrete_moves`] for `player` (usually [`PLAYER_RAND`]) in
+    /// the same order.
+    #[allow(unused_variables)]
+    fn get_concrete_move_probabilities(
+        &mut self,
+        player: player_id,
+        probabilities: &mut PtrVec<f32>,
+    ) -> Result<()> {
+        unimplemented!("get_concrete_move_probabilities")
+    }
+    /// Must be implemented when the [`game_feature_flags::random_moves`] is
+    /// enabled.
+    ///
+    /// Returns a move for [`PLAYER_RAND`] which is drawn deterministically
+    /// from the given `seed`.
+    #[allow(unused_variables)]
+    fn get_random_move(&mut self, seed: u64) -> Result<move_code> {
+        unimplemented!("get_random_move")
+    }
+    /// Must be implemented when the [`game_feature_flags::move_ordering`] is
+    /// enabled.
+    ///
+    /// Writes the same moves as [`Self::get_concrete_moves`] but ordered from
+    /// the most to the least promising one.
+    /// This is exported as `get_concrete_moves_ordered`.
+    #[allow(unused_variables)]
+    fn get_move_ordering(
+        &mut self,
+        player: player_id,
+        moves: &mut PtrVec<move_code>,
+    ) -> Result<()> {
+        unimplemented!("get_move_ordering")
+    }
+    /// Must be implemented when [`Self::ACTIONS`] is `true`.
+    ///
+    /// Writes the actions available to `player`.
+    /// Actions are abstractions of moves (e.g., "draw a card" instead of the
+    /// concrete card drawn).
+    /// At most [`buf_sizer::max_actions`] actions can be written.
+    #[allow(unused_variables)]
+    fn get_actions(&mut self, player: player_id, actions: &mut PtrVec<move_code>) -> Result<()> {
+        unimplemented!("get_actions")
+    }
+    /// Must be implemented when [`Self::ACTIONS`] is `true`.
+    ///
+    /// Returns whether `mov` is an action instead of a concrete move.
+    #[allow(unused_variables)]
+    #[allow(clippy::wrong_self_convention)]
+    fn is_action(&mut self, mov: move_code) -> Result<bool> {
+        unimplemented!("is_action")
+    }
+    /// Must be implemented when [`Self::ACTIONS`] is `true`.
+    ///
+    /// Returns the action which abstracts the concrete move `mov`.
+    #[allow(unused_variables)]
+    fn move_to_action(&mut self, mov: move_code) -> Result<move_code> {
+        unimplemented!("move_to_action")
+    }
+    /// Must be implemented when the [`game_feature_flags::random_moves`] is
+    /// enabled.
+    ///
+    /// Fixes the outcome of all future random events using `seed`.
+    /// Random events are the moves of [`PLAYER_RAND`], hence this is tied to
+    /// random moves instead of actions.
+    #[allow(unused_variables)]
+    fn discretize(&mut self, seed: u64) -> Result<()> {
+        unimplemented!("discretize")
+    }
+    /// Must be implemented when the [`game_feature_flags::legacy`] is enabled.
+    ///
+    /// Exports the data which is carried over into the next match (see
+    /// [`Self::create`]).
+    /// The written length must not exceed [`buf_sizer::legacy_str`]` - 1`.
+    #[allow(unused_variables)]
+    fn export_legacy(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        unimplemented!("export_legacy")
+    }
+    /// Must be implemented when the [`game_feature_flags::scores`] is enabled.
+    ///
+    /// The score at index `i` belongs to player `i + 1`.
+    /// Hence, `scores` has a capacity of [`buf_sizer::player_count`].
+    #[allow(unused_variables)]
+    fn get_scores(&mut self, scores: &mut PtrVec<i32>) -> Result<()> {
+        unimplemented!("get_scores")
+    }
+    /// Must be implemented when the [`game_feature_flags::id`] is enabled.
+    ///
+    /// Returns an identifier of the current state, which must be equal for
+    /// equal states.
+    /// See the [`hash`] module for a helper.
+    fn get_id(&mut self) -> Result<u64> {
+        unimplemented!("get_id")
+    }
+    /// Must be implemented when the [`game_feature_flags::eval`] is enabled.
+    ///
+    /// Returns a static evaluation of the current state from the perspective
+    /// of `player`.
+    /// Larger values are better for `player`.
+    #[allow(unused_variables)]
+    fn get_eval(&mut self, player: player_id) -> Result<f32> {
+        unimplemented!("get_eval")
+    }
+    /// Used when the [`game_feature_flags::playout`] is enabled.
+    ///
+    /// Plays the game from the current state until it is over using moves
+    /// derived from `seed` and writes the winners into `winners` if given.
+    /// Returns whether the game is over.
+    /// After `max_moves` moves, the playout stops and returns `false` without
+    /// writing winners.
+    /// `sizer` is the [`buf_sizer`] returned by [`Self::create`].
+    ///
+    /// The C API plays until the game is over by passing [`usize::MAX`] as
+    /// `max_moves` and reports the winners via `get_results`.
+    ///
+    /// The default implementation uses [`playout::random_playout`].
+    fn playout(
+        &mut self,
+        seed: u64,
+        sizer: &buf_sizer,
+        max_moves: usize,
+        sync_ctr: &mut sync_counter,
+        winners: Option<&mut PtrVec<player_id>>,
+    ) -> Result<bool> {
+        playout::random_playout(self, seed, sizer, max_moves, sync_ctr, winners)
+    }
+    /// Must be implemented when the [`game_feature_flags::hidden_information`]
+    /// is enabled.
+    ///
+    /// Removes all hidden information which is not visible to any of the
+    /// `players`.
+    #[allow(unused_variables)]
+    fn redact_keep_state(&mut self, players: &[player_id]) -> Result<()> {
+        unimplemented!("redact_keep_state")
+    }
+    /// Must be implemented when the [`game_feature_flags::hidden_information`]
+    /// is enabled.
+    ///
+    /// Returns the data which needs to be sent to other players since the last
+    /// call.
+    /// The wrapper keeps the returned data alive until surena releases it.
+    fn export_sync_data(&mut self) -> Result<Vec<SyncData>> {
+        unimplemented!("export_sync_data")
+    }
+    /// Must be implemented when the [`game_feature_flags::hidden_information`]
+    /// is enabled.
+    ///
+    /// Imports the [`SyncData::data`] exported by another instance.
+    #[allow(unused_variables)]
+    fn import_sync_data(&mut self, data: &[u8]) -> Result<()> {
+        unimplemented!("import_sync_data")
+    }
 
     #[doc(hidden)]
     unsafe extern "C" fn get_last_error_wrapped(game: *mut sys::game) -> *const c_char {
@@ -156,13 +371,16 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         data1.write(null_mut());
         Aux::init(game);
 
-        let (data, sizer) = surena_try!(game, Self::create(&GameInit::new(&*init_info)));
-        check_sizer(&sizer, get_features(game));
-        addr_of_mut!((*game).sizer).write(sizer);
-        // data1 is already initialized.
-        *data1 = Box::into_raw(Box::new(data)).cast();
+        guard(game, || {
+            let (data, sizer) = surena_try!(game, Self::create(&GameInit::new(&*init_info)));
+            check_sizer(&sizer, get_features(game), Self::ACTIONS);
+            addr_of_mut!((*game).sizer).write(sizer);
+            addr_of_mut!((*game).sync_ctr).write(SYNC_COUNTER_DEFAULT);
+            // data1 is already initialized.
+            *data1 = Box::into_raw(Box::new(data)).cast();
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -171,24 +389,31 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_size: *mut usize,
         str_buf: *mut c_char,
     ) -> sys::error_code {
-        let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).options_str);
-        surena_try!(game, get_data::<Self>(game).export_options(&mut ptr_vec));
-        str_buf.add(*ret_size).write(0);
+        guard(game, || {
+            let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).options_str);
+            surena_try!(game, get_data::<Self>(game).export_options(&mut ptr_vec));
+            str_buf.add(*ret_size).write(0);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
     unsafe extern "C" fn destroy_wrapped(game: *mut sys::game) -> sys::error_code {
         let data: &mut *mut c_void = &mut *addr_of_mut!((*game).data1);
+        let mut code = sys::ERR_ERR_OK;
         if !data.is_null() {
-            drop(Box::from_raw(data.cast::<Self>()));
+            let ptr = data.cast::<Self>();
+            // Memory must be freed even if the instance is poisoned.
+            if catch_unwind(AssertUnwindSafe(move || drop(Box::from_raw(ptr)))).is_err() {
+                code = ErrorCode::StateUnrecoverable.into();
+            }
             // Leave as null pointer to catch use-after-free errors.
             *data = null_mut();
         }
         Aux::free(game);
 
-        sys::ERR_ERR_OK
+        code
     }
 
     #[doc(hidden)]
@@ -203,11 +428,13 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         data1.write(null_mut());
         Aux::init(clone_target);
 
-        let data = get_data::<Self>(game).clone();
-        // data1 is already initialized.
-        *data1 = Box::into_raw(Box::new(data)).cast();
+        guard(game, || {
+            let data = get_data::<Self>(game).clone();
+            // data1 is already initialized.
+            *data1 = Box::into_raw(Box::new(data)).cast();
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -215,10 +442,15 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         game: *mut sys::game,
         other: *mut sys::game,
     ) -> sys::error_code {
-        let other = get_data::<Self>(other);
-        surena_try!(game, get_data::<Self>(game).copy_from(other));
+        guard(game, || {
+            surena_try!(game, check_other(other));
+            let sync_ctr = *addr_of!((*other).sync_ctr);
+            let other = get_data::<Self>(other);
+            surena_try!(game, get_data::<Self>(game).copy_from(other));
+            addr_of_mut!((*game).sync_ctr).write(sync_ctr);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -227,10 +459,13 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         other: *mut sys::game,
         ret_equal: *mut bool,
     ) -> sys::error_code {
-        let other = get_data::<Self>(other);
-        ret_equal.write(get_data::<Self>(game).eq(&other));
+        guard(game, || {
+            surena_try!(game, check_other(other));
+            let other = get_data::<Self>(other);
+            ret_equal.write(get_data::<Self>(game).eq(&other));
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -238,10 +473,12 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         game: *mut sys::game,
         string: *const c_char,
     ) -> sys::error_code {
-        let string = cstr_to_rust(string);
-        surena_try!(game, get_data::<Self>(game).import_state(string));
+        guard(game, || {
+            let string = cstr_to_rust(string);
+            surena_try!(game, get_data::<Self>(game).import_state(string));
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -250,11 +487,27 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_size: *mut usize,
         str_buf: *mut c_char,
     ) -> sys::error_code {
-        let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).state_str);
-        surena_try!(game, get_data::<Self>(game).export_state(&mut ptr_vec));
-        str_buf.add(*ret_size).write(0);
+        guard(game, || {
+            let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).state_str);
+            surena_try!(game, get_data::<Self>(game).export_state(&mut ptr_vec));
+            str_buf.add(*ret_size).write(0);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn serialize_wrapped(
+        game: *mut sys::game,
+        ret_size: *mut usize,
+        buf: *mut c_char,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut ptr_vec = PtrVec::new(buf.cast(), &mut *ret_size, get_sizer(game).serialize);
+            surena_try!(game, get_data::<Self>(game).serialize(&mut ptr_vec));
+
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -263,16 +516,23 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_count: *mut u8,
         players: *mut player_id,
     ) -> sys::error_code {
-        let mut len = 0;
-        let mut players = PtrVec::new(
-            players,
-            &mut len,
-            get_sizer(game).max_players_to_move.into(),
-        );
-        surena_try!(game, get_data::<Self>(game).players_to_move(&mut players));
-        ret_count.write(len as u8);
+        guard(game, || {
+            let mut len = 0;
+            // The capacity ensures that max_players_to_move is never exceeded.
+            let mut ptr_vec = PtrVec::new(
+                players,
+                &mut len,
+                get_sizer(game).max_players_to_move.into(),
+            );
+            surena_try!(game, get_data::<Self>(game).players_to_move(&mut ptr_vec));
+            surena_try!(
+                game,
+                check_players_to_move(raw_slice(players, len), get_features(game))
+            );
+            ret_count.write(len as u8);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -282,15 +542,72 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_count: *mut u32,
         moves: *mut move_code,
     ) -> sys::error_code {
-        let mut len = 0;
-        let mut moves = PtrVec::new(moves, &mut len, get_sizer(game).max_moves as usize);
-        surena_try!(
-            game,
-            get_data::<Self>(game).get_concrete_moves(player, &mut moves)
-        );
-        ret_count.write(len as u32);
+        guard(game, || {
+            let mut len = 0;
+            let mut moves = PtrVec::new(moves, &mut len, get_sizer(game).max_moves as usize);
+            surena_try!(
+                game,
+                get_data::<Self>(game).get_concrete_moves(player, &mut moves)
+            );
+            ret_count.write(len as u32);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_concrete_move_probabilities_wrapped(
+        game: *mut sys::game,
+        player: player_id,
+        ret_count: *mut u32,
+        probabilities: *mut f32,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut len = 0;
+            let mut probabilities =
+                PtrVec::new(probabilities, &mut len, get_sizer(game).max_moves as usize);
+            surena_try!(
+                game,
+                get_data::<Self>(game).get_concrete_move_probabilities(player, &mut probabilities)
+            );
+            ret_count.write(len as u32);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_random_move_wrapped(
+        game: *mut sys::game,
+        seed: u64,
+        ret_move: *mut move_code,
+    ) -> sys::error_code {
+        guard(game, || {
+            let result = surena_try!(game, get_data::<Self>(game).get_random_move(seed));
+            ret_move.write(result);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_move_ordering_wrapped(
+        game: *mut sys::game,
+        player: player_id,
+        ret_count: *mut u32,
+        moves: *mut move_code,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut len = 0;
+            let mut moves = PtrVec::new(moves, &mut len, get_sizer(game).max_moves as usize);
+            surena_try!(
+                game,
+                get_data::<Self>(game).get_move_ordering(player, &mut moves)
+            );
+            ret_count.write(len as u32);
+
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -299,9 +616,72 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         player: player_id,
         mov: move_code,
     ) -> sys::error_code {
-        surena_try!(game, get_data::<Self>(game).is_legal_move(player, mov));
+        guard(game, || {
+            let sync_ctr = *addr_of!((*game).sync_ctr);
+            surena_try!(
+                game,
+                get_data::<Self>(game).is_legal_move(player, mov, sync_ctr)
+            );
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_actions_wrapped(
+        game: *mut sys::game,
+        player: player_id,
+        ret_count: *mut u32,
+        actions: *mut move_code,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut len = 0;
+            let mut actions = PtrVec::new(actions, &mut len, get_sizer(game).max_actions as usize);
+            surena_try!(
+                game,
+                get_data::<Self>(game).get_actions(player, &mut actions)
+            );
+            ret_count.write(len as u32);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn is_action_wrapped(
+        game: *mut sys::game,
+        mov: move_code,
+        ret_is_action: *mut bool,
+    ) -> sys::error_code {
+        guard(game, || {
+            let result = surena_try!(game, get_data::<Self>(game).is_action(mov));
+            ret_is_action.write(result);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn move_to_action_wrapped(
+        game: *mut sys::game,
+        mov: move_code,
+        ret_action: *mut move_code,
+    ) -> sys::error_code {
+        guard(game, || {
+            let result = surena_try!(game, get_data::<Self>(game).move_to_action(mov));
+            ret_action.write(result);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn discretize_wrapped(game: *mut sys::game, seed: u64) -> sys::error_code {
+        guard(game, || {
+            surena_try!(game, get_data::<Self>(game).discretize(seed));
+
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -310,9 +690,15 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         player: player_id,
         mov: move_code,
     ) -> sys::error_code {
-        surena_try!(game, get_data::<Self>(game).make_move(player, mov));
+        guard(game, || {
+            let sync_ctr = &mut *addr_of_mut!((*game).sync_ctr);
+            surena_try!(
+                game,
+                get_data::<Self>(game).make_move(player, mov, sync_ctr)
+            );
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -321,12 +707,156 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_count: *mut u8,
         players: *mut player_id,
     ) -> sys::error_code {
-        let mut len = 0;
-        let mut players = PtrVec::new(players, &mut len, get_sizer(game).max_results.into());
-        surena_try!(game, get_data::<Self>(game).get_results(&mut players));
-        ret_count.write(len as u8);
+        guard(game, || {
+            let mut len = 0;
+            let mut players = PtrVec::new(players, &mut len, get_sizer(game).max_results.into());
+            surena_try!(game, get_data::<Self>(game).get_results(&mut players));
+            ret_count.write(len as u8);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn export_legacy_wrapped(
+        game: *mut sys::game,
+        ret_size: *mut usize,
+        str_buf: *mut c_char,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).legacy_str);
+            surena_try!(game, get_data::<Self>(game).export_legacy(&mut ptr_vec));
+            str_buf.add(*ret_size).write(0);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_scores_wrapped(
+        game: *mut sys::game,
+        ret_count: *mut usize,
+        players: *mut player_id,
+        scores: *mut i32,
+    ) -> sys::error_code {
+        guard(game, || {
+            let mut len = 0;
+            let mut ptr_vec = PtrVec::new(scores, &mut len, get_sizer(game).player_count.into());
+            surena_try!(game, get_data::<Self>(game).get_scores(&mut ptr_vec));
+            for (i, player) in (1..=len as player_id).enumerate() {
+                players.add(i).write(player);
+            }
+            ret_count.write(len);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_id_wrapped(game: *mut sys::game, ret_id: *mut u64) -> sys::error_code {
+        guard(game, || {
+            let result = surena_try!(game, get_data::<Self>(game).get_id());
+            ret_id.write(result);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn get_eval_wrapped(
+        game: *mut sys::game,
+        player: player_id,
+        ret_eval: *mut f32,
+    ) -> sys::error_code {
+        guard(game, || {
+            let result = surena_try!(game, get_data::<Self>(game).get_eval(player));
+            ret_eval.write(result);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn playout_wrapped(game: *mut sys::game, seed: u64) -> sys::error_code {
+        guard(game, || {
+            let sync_ctr = &mut *addr_of_mut!((*game).sync_ctr);
+            // The C API only reports the winners via get_results.
+            let over = surena_try!(
+                game,
+                get_data::<Self>(game).playout(seed, get_sizer(game), usize::MAX, sync_ctr, None)
+            );
+            // Only a custom playout ignoring max_moves can stop early.
+            if !over {
+                let error = Error::new_static(
+                    ErrorCode::InvalidState,
+                    "playout stopped before the game was over\0",
+                );
+                Aux::get(game).set_error(error.message);
+                return error.code.into();
+            }
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn redact_keep_state_wrapped(
+        game: *mut sys::game,
+        count: u8,
+        players: *mut player_id,
+    ) -> sys::error_code {
+        guard(game, || {
+            let players = raw_slice(players, count.into());
+            surena_try!(game, get_data::<Self>(game).redact_keep_state(players));
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn export_sync_data_wrapped(
+        game: *mut sys::game,
+        sync_data_start: *mut *mut sys::sync_data,
+        sync_data_end: *mut *mut sys::sync_data,
+    ) -> sys::error_code {
+        guard(game, || {
+            let data = surena_try!(game, get_data::<Self>(game).export_sync_data());
+            let mut exported = surena_try!(game, ExportedSyncData::new(data));
+            let range = exported.raw.as_mut_ptr_range();
+            sync_data_start.write(range.start);
+            sync_data_end.write(range.end);
+            Aux::get(game).sync_data.push(exported);
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn release_sync_data_wrapped(
+        game: *mut sys::game,
+        sync_data_start: *mut sys::sync_data,
+        _sync_data_end: *mut sys::sync_data,
+    ) -> sys::error_code {
+        guard(game, || {
+            surena_try!(game, Aux::get(game).release_sync_data(sync_data_start));
+
+            sys::ERR_ERR_OK
+        })
+    }
+
+    #[doc(hidden)]
+    unsafe extern "C" fn import_sync_data_wrapped(
+        game: *mut sys::game,
+        data_start: *mut c_void,
+        data_end: *mut c_void,
+    ) -> sys::error_code {
+        guard(game, || {
+            let len = (data_end as usize).saturating_sub(data_start as usize);
+            let data = raw_slice(data_start.cast::<u8>(), len);
+            surena_try!(game, get_data::<Self>(game).import_sync_data(data));
+
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -336,11 +866,13 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         string: *const c_char,
         ret_move: *mut move_code,
     ) -> sys::error_code {
-        let string = cstr_to_rust_unchecked(string);
-        let result = surena_try!(game, get_data::<Self>(game).get_move_code(player, string));
-        ret_move.write(result);
+        guard(game, || {
+            let string = cstr_to_rust_unchecked(string);
+            let result = surena_try!(game, get_data::<Self>(game).get_move_code(player, string));
+            ret_move.write(result);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -351,14 +883,16 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_size: *mut usize,
         str_buf: *mut c_char,
     ) -> sys::error_code {
-        let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).move_str);
-        surena_try!(
-            game,
-            get_data::<Self>(game).get_move_str(player, mov, &mut ptr_vec)
-        );
-        str_buf.add(*ret_size).write(0);
+        guard(game, || {
+            let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).move_str);
+            surena_try!(
+                game,
+                get_data::<Self>(game).get_move_str(player, mov, &mut ptr_vec)
+            );
+            str_buf.add(*ret_size).write(0);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 
     #[doc(hidden)]
@@ -367,11 +901,13 @@ pub trait GameMethods: Sized + Clone + Eq + Send {
         ret_size: *mut usize,
         str_buf: *mut c_char,
     ) -> sys::error_code {
-        let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).print_str);
-        surena_try!(game, get_data::<Self>(game).print(&mut ptr_vec));
-        str_buf.add(*ret_size).write(0);
+        guard(game, || {
+            let mut ptr_vec = StrBuf::from_c_char(str_buf, ret_size, get_sizer(game).print_str);
+            surena_try!(game, get_data::<Self>(game).print(&mut ptr_vec));
+            str_buf.add(*ret_size).write(0);
 
-        sys::ERR_ERR_OK
+            sys::ERR_ERR_OK
+        })
     }
 }
 
@@ -433,13 +969,99 @@ pub fn create_game_methods<G: GameMethods>(metadata: Metadata) -> game_methods {
         compare: Some(G::compare_wrapped),
         import_state: Some(G::import_state_wrapped),
         export_state: Some(G::export_state_wrapped),
+        serialize: if metadata.features.serializable() {
+            Some(G::serialize_wrapped)
+        } else {
+            None
+        },
         players_to_move: Some(G::players_to_move_wrapped),
         get_concrete_moves: Some(G::get_concrete_moves_wrapped),
+        get_concrete_move_probabilities: if metadata.features.random_moves() {
+            Some(G::get_concrete_move_probabilities_wrapped)
+        } else {
+            None
+        },
+        get_random_move: if metadata.features.random_moves() {
+            Some(G::get_random_move_wrapped)
+        } else {
+            None
+        },
+        get_concrete_moves_ordered: if metadata.features.move_ordering() {
+            Some(G::get_move_ordering_wrapped)
+        } else {
+            None
+        },
+        get_actions: if G::ACTIONS {
+            Some(G::get_actions_wrapped)
+        } else {
+            None
+        },
         is_legal_move: Some(G::is_legal_move_wrapped),
+        move_to_action: if G::ACTIONS {
+            Some(G::move_to_action_wrapped)
+        } else {
+            None
+        },
+        is_action: if G::ACTIONS {
+            Some(G::is_action_wrapped)
+        } else {
+            None
+        },
         make_move: Some(G::make_move_wrapped),
         get_results: Some(G::get_results_wrapped),
         get_move_code: Some(G::get_move_code_wrapped),
         get_move_str: Some(G::get_move_str_wrapped),
+        export_legacy: if metadata.features.legacy() {
+            Some(G::export_legacy_wrapped)
+        } else {
+            None
+        },
+        get_scores: if metadata.features.scores() {
+            Some(G::get_scores_wrapped)
+        } else {
+            None
+        },
+        id: if metadata.features.id() {
+            Some(G::get_id_wrapped)
+        } else {
+            None
+        },
+        eval: if metadata.features.eval() {
+            Some(G::get_eval_wrapped)
+        } else {
+            None
+        },
+        // Discretizing fixes the outcomes of random moves.
+        discretize: if metadata.features.random_moves() {
+            Some(G::discretize_wrapped)
+        } else {
+            None
+        },
+        playout: if metadata.features.playout() {
+            Some(G::playout_wrapped)
+        } else {
+            None
+        },
+        redact_keep_state: if metadata.features.hidden_information() {
+            Some(G::redact_keep_state_wrapped)
+        } else {
+            None
+        },
+        export_sync_data: if metadata.features.hidden_information() {
+            Some(G::export_sync_data_wrapped)
+        } else {
+            None
+        },
+        release_sync_data: if metadata.features.hidden_information() {
+            Some(G::release_sync_data_wrapped)
+        } else {
+            None
+        },
+        import_sync_data: if metadata.features.hidden_information() {
+            Some(G::import_sync_data_wrapped)
+        } else {
+            None
+        },
         print: if metadata.features.print() {
             Some(G::print_wrapped)
         } else {
@@ -449,9 +1071,57 @@ pub fn create_game_methods<G: GameMethods>(metadata: Metadata) -> game_methods {
     }
 }
 
+/// Data for synchronizing hidden information between game instances.
+///
+/// See [`GameMethods::export_sync_data`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncData {
+    /// The players which should receive this data.
+    pub players: Vec<player_id>,
+    /// Opaque data which is passed to [`GameMethods::import_sync_data`].
+    pub data: Vec<u8>,
+}
+
+/// Sync data handed out to surena until it is released again.
+struct ExportedSyncData {
+    /// C structs pointing into `data`.
+    raw: Box<[sys::sync_data]>,
+    /// Owner of the memory referenced by `raw`.
+    ///
+    /// The heap buffers of the [`Vec`]s do not move when this is moved.
+    _data: Vec<SyncData>,
+}
+
+impl ExportedSyncData {
+    fn new(mut data: Vec<SyncData>) -> Result<Self> {
+        let raw = data
+            .iter_mut()
+            .map(|d| {
+                Ok(sys::sync_data {
+                    player_c: d.players.len().try_into().map_err(|_| {
+                        Error::new_static(
+                            ErrorCode::InvalidPlayer,
+                            "too many players for sync data\0",
+                        )
+                    })?,
+                    players: d.players.as_mut_ptr(),
+                    data_len: d.data.len(),
+                    data: d.data.as_mut_ptr().cast(),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { raw, _data: data })
+    }
+}
+
 #[derive(Default)]
 struct Aux {
     error: ErrorString,
+    /// Set when the game panicked and its state might be inconsistent.
+    poisoned: bool,
+    /// Sync data which has not been released by surena yet.
+    sync_data: Vec<ExportedSyncData>,
 }
 
 impl Aux {
@@ -482,6 +1152,17 @@ impl Aux {
     fn set_error(&mut self, error: ErrorString) {
         self.error = error;
     }
+
+    /// Frees the sync data previously exported starting at `start`.
+    fn release_sync_data(&mut self, start: *mut sys::sync_data) -> Result<()> {
+        let index = self
+            .sync_data
+            .iter_mut()
+            .position(|d| d.raw.as_mut_ptr() == start)
+            .ok_or_else(|| Error::new_static(ErrorCode::InvalidInput, "unknown sync data\0"))?;
+        self.sync_data.swap_remove(index);
+        Ok(())
+    }
 }
 
 #[inline]
@@ -502,19 +1183,154 @@ unsafe fn get_sizer<'l>(game: *mut sys::game) -> &'l buf_sizer {
     &*addr_of!((*game).sizer)
 }
 
-fn check_sizer(sizer: &buf_sizer, features: game_feature_flags) {
+/// Runs the `body` of a `[...]_wrapped` function and catches panics.
+///
+/// A panic poisons the game instance, so that all later calls fail instead of
+/// operating on a possibly inconsistent state.
+unsafe fn guard(game: *mut sys::game, body: impl FnOnce() -> sys::error_code) -> sys::error_code {
+    if Aux::get(game).poisoned {
+        let error = Error::new_static(
+            ErrorCode::StateUnrecoverable,
+            "game is poisoned by an earlier panic\0",
+        );
+        Aux::get(game).set_error(error.message);
+        return error.code.into();
+    }
+
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(code) => code,
+        Err(payload) => {
+            let error = Error::new_dynamic(
+                ErrorCode::StateUnrecoverable,
+                format!("game panicked: {}", panic_message(&*payload)),
+            );
+            let aux = Aux::get(game);
+            aux.poisoned = true;
+            aux.set_error(error.message);
+            error.code.into()
+        }
+    }
+}
+
+/// Fails if the `other` game instance of a binary operation is poisoned.
+///
+/// Its state might be inconsistent and must not be read.
+unsafe fn check_other(other: *mut sys::game) -> Result<()> {
+    if Aux::get(other).poisoned {
+        return Err(Error::new_static(
+            ErrorCode::StateUnrecoverable,
+            "other game is poisoned by an earlier panic\0",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the message of a panic `payload`.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic payload")
+}
+
+/// Returns the message of `error` or its code if there is none.
+pub fn describe(error: &Error) -> String {
+    let message: *const c_char = (&error.message).into();
+    if message.is_null() {
+        format!("{:?}", error.code)
+    } else {
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Creates a slice from a C array which might be a null pointer if empty.
+#[inline]
+unsafe fn raw_slice<'l, T>(ptr: *const T, len: usize) -> &'l [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
+    }
+}
+
+/// Checks that the players to move are consistent with the `features`.
+fn check_players_to_move(players: &[player_id], features: game_feature_flags) -> Result<()> {
+    if players.len() > 1 && !features.simultaneous_moves() {
+        return Err(Error::new_static(
+            ErrorCode::StateCorrupted,
+            "multiple players to move without simultaneous moves\0",
+        ));
+    }
+    if players.contains(&PLAYER_NONE) {
+        return Err(Error::new_static(
+            ErrorCode::StateCorrupted,
+            "PLAYER_NONE cannot be to move\0",
+        ));
+    }
+    for (i, player) in players.iter().enumerate() {
+        if players[..i].contains(player) {
+            return Err(Error::new_static(
+                ErrorCode::StateCorrupted,
+                "duplicate player to move\0",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_sizer(sizer: &buf_sizer, features: game_feature_flags, actions: bool) {
     const FAILURE: &str = "string buffer length must not be 0";
 
     if features.options() {
         assert!(sizer.options_str > 0, "{FAILURE}");
     }
     assert!(sizer.state_str > 0, "{FAILURE}");
+    if features.serializable() {
+        assert!(
+            sizer.serialize > 0,
+            "serialization buffer length must not be 0"
+        );
+    }
     // This can only happen on <32bit platforms:
     let _: usize = sizer
         .max_moves
         .try_into()
         .expect("max_moves does not fit usize");
+    let _: usize = sizer
+        .max_actions
+        .try_into()
+        .expect("max_actions does not fit usize");
     assert!(sizer.move_str > 0, "{FAILURE}");
+    if actions {
+        assert!(
+            sizer.max_actions > 0,
+            "max_actions must not be 0 with actions"
+        );
+    }
+    if features.random_moves() {
+        // PLAYER_RAND must be able to appear in players_to_move and needs at
+        // least one move to choose from.
+        assert!(
+            sizer.max_players_to_move > 0,
+            "max_players_to_move must not be 0 with random moves"
+        );
+        assert!(
+            sizer.max_moves > 0,
+            "max_moves must not be 0 with random moves"
+        );
+    }
+    if features.legacy() {
+        assert!(sizer.legacy_str > 0, "{FAILURE}");
+    }
+    if features.scores() {
+        assert!(
+            sizer.player_count > 0,
+            "player_count must not be 0 with scores"
+        );
+    }
     if features.print() {
         assert!(sizer.print_str > 0, "{FAILURE}");
     }
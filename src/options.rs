@@ -0,0 +1,377 @@
+//! Typed, declarative game options.
+//!
+//! The [`game_options!`](crate::game_options) macro declares a struct of
+//! options and implements [`Options`] for it.
+//! Options are exported as values separated by single spaces in declaration
+//! order, e.g., `"21 3 true"`.
+//! When parsing, missing trailing values keep their defaults.
+//!
+//! Supported value types are integers, [`bool`], and enums declared via
+//! [`option_enum!`](crate::option_enum).
+//!
+//! # Example
+//! ```
+//! use surena_game::{game_options, option_enum, options::Options, GameInit};
+//!
+//! option_enum! {
+//!     /// Which player wins when the counter reaches zero.
+//!     pub enum Winner {
+//!         Last = "last",
+//!         Other = "other",
+//!     }
+//! }
+//!
+//! game_options! {
+//!     pub struct NimOptions {
+//!         /// Starting value of the counter.
+//!         #[range(1, 1000)]
+//!         pub counter: u16 = 21,
+//!         #[range(1, 10)]
+//!         pub max_sub: u16 = 3,
+//!         pub winner: Winner = Winner::Other,
+//!     }
+//! }
+//!
+//! let options = NimOptions::parse("30")?;
+//! assert_eq!(30, options.counter);
+//! assert_eq!(3, options.max_sub);
+//! assert_eq!(Winner::Other, options.winner);
+//! assert!(NimOptions::parse("30 11").is_err());
+//!
+//! let default = NimOptions::from_init(&GameInit::Default)?;
+//! assert_eq!(NimOptions::default(), default);
+//! // "1000 10 other" and the NUL byte
+//! assert_eq!(14, NimOptions::options_str());
+//! # Ok::<(), surena_game::Error>(())
+//! ```
+
+use crate::{Error, ErrorCode, GameInit, Result, StrBuf};
+
+use std::fmt::{self, Display, Write};
+
+/// A set of options declared via [`game_options!`](crate::game_options).
+pub trait Options: Sized + Default {
+    /// Parses the whitespace-separated `string`.
+    ///
+    /// Errors are reported as [`ErrorCode::InvalidOptions`].
+    fn parse(string: &str) -> Result<Self>;
+    /// Writes the options in a format accepted by [`Self::parse`].
+    fn export(&self, str_buf: &mut StrBuf) -> Result<()>;
+    /// Size of the exported options including the NUL byte.
+    ///
+    /// This can be used as
+    /// [`buf_sizer::options_str`](crate::buf_sizer::options_str).
+    fn options_str() -> usize;
+
+    /// Parses `opts` or returns the defaults for [`None`].
+    fn from_opts(opts: Option<&str>) -> Result<Self> {
+        opts.map(Self::parse)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Parses the `opts` of [`GameInit::Standard`].
+    ///
+    /// Returns the defaults for [`GameInit::Default`] and an error for
+    /// [`GameInit::Serialized`], which needs to be handled by the game.
+    fn from_init(init_info: &GameInit) -> Result<Self> {
+        match init_info {
+            GameInit::Default => Ok(Self::default()),
+            GameInit::Standard { opts, .. } => Self::from_opts(*opts),
+            GameInit::Serialized(_) => Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "options cannot be parsed from serialized data\0",
+            )),
+        }
+    }
+}
+
+/// A type which can be used as option value.
+pub trait OptionValue: Copy + PartialOrd + Display {
+    /// Smallest value if no range is declared.
+    const MIN: Self;
+    /// Largest value if no range is declared.
+    const MAX: Self;
+
+    /// Parses a single value, returning a description of the problem on
+    /// failure.
+    fn parse_value(string: &str) -> std::result::Result<Self, String>;
+    /// Maximum length of the string of any value between `min` and `max`.
+    fn max_len(min: Self, max: Self) -> usize;
+}
+
+macro_rules! impl_option_value_int {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl OptionValue for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn parse_value(string: &str) -> std::result::Result<Self, String> {
+                    string.parse().map_err(|e| format!("{e}"))
+                }
+
+                fn max_len(min: Self, max: Self) -> usize {
+                    // The extremes have the most digits or a minus sign.
+                    min.to_string().len().max(max.to_string().len())
+                }
+            }
+        )*
+    };
+}
+
+impl_option_value_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl OptionValue for bool {
+    const MIN: Self = false;
+    const MAX: Self = true;
+
+    fn parse_value(string: &str) -> std::result::Result<Self, String> {
+        match string {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err("expected true or false".to_string()),
+        }
+    }
+
+    fn max_len(_min: Self, _max: Self) -> usize {
+        "false".len()
+    }
+}
+
+/// This macro declares an enum which can be used in
+/// [`game_options!`](crate::game_options).
+///
+/// Each variant is assigned the string used for parsing and exporting it.
+/// The variants are ordered by declaration.
+///
+/// # Example
+/// ```
+/// surena_game::option_enum! {
+///     pub enum Board {
+///         Small = "small",
+///         Large = "large",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! option_enum {
+    (
+        $( #[$meta:meta] )*
+        $vis:vis enum $name:ident {
+            $( $( #[$vmeta:meta] )* $variant:ident = $string:literal ),* $(,)?
+        }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis enum $name {
+            $( $( #[$vmeta] )* $variant ),*
+        }
+
+        impl $name {
+            const VARIANTS: &'static [(Self, &'static str)] = &[$( (Self::$variant, $string) ),*];
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                let (_, string) = Self::VARIANTS
+                    .iter()
+                    .find(|(v, _)| v == self)
+                    .expect("all variants are listed");
+                f.write_str(string)
+            }
+        }
+
+        impl $crate::options::OptionValue for $name {
+            const MIN: Self = Self::VARIANTS[0].0;
+            const MAX: Self = Self::VARIANTS[Self::VARIANTS.len() - 1].0;
+
+            fn parse_value(string: &str) -> ::std::result::Result<Self, String> {
+                Self::VARIANTS
+                    .iter()
+                    .find(|(_, s)| *s == string)
+                    .map(|(v, _)| *v)
+                    .ok_or_else(|| {
+                        let names: Vec<_> = Self::VARIANTS.iter().map(|(_, s)| *s).collect();
+                        format!("expected one of {}", names.join(", "))
+                    })
+            }
+
+            fn max_len(_min: Self, _max: Self) -> usize {
+                Self::VARIANTS.iter().map(|(_, s)| s.len()).max().unwrap_or_default()
+            }
+        }
+    };
+}
+
+/// This macro declares a struct of typed options and implements [`Options`]
+/// and [`Default`] for it.
+///
+/// Each field has a type implementing [`OptionValue`] and a default value.
+/// The accepted range of a field can be restricted with `#[range(min, max)]`
+/// after its doc comments.
+/// [`Default::default`] panics if a default lies outside of its range, so
+/// such mistakes surface in the first test using the options.
+///
+/// See the [module documentation](crate::options) for an example.
+///
+/// ```should_panic
+/// surena_game::game_options! {
+///     struct Broken {
+///         #[range(1, 10)]
+///         size: u8 = 0,
+///     }
+/// }
+///
+/// let _ = Broken::default();
+/// ```
+#[macro_export]
+macro_rules! game_options {
+    (
+        $( #[$meta:meta] )*
+        $vis:vis struct $name:ident {
+            $(
+                $( #[doc = $doc:literal] )*
+                $( #[range($min:expr, $max:expr)] )?
+                $fvis:vis $field:ident : $t:ty = $default:expr
+            ),* $(,)?
+        }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        $vis struct $name {
+            $( $( #[doc = $doc] )* $fvis $field: $t ),*
+        }
+
+        impl ::std::default::Default for $name {
+            fn default() -> Self {
+                let options = Self { $( $field: $default ),* };
+                $(
+                    $crate::options::check_default(
+                        stringify!($field),
+                        options.$field,
+                        $crate::game_options!(@min $t $(, $min)?),
+                        $crate::game_options!(@max $t $(, $max)?),
+                    );
+                )*
+                options
+            }
+        }
+
+        impl $crate::options::Options for $name {
+            fn parse(string: &str) -> $crate::Result<Self> {
+                let mut values = string.split_whitespace();
+                let mut options = Self::default();
+                $(
+                    if let Some(value) = values.next() {
+                        options.$field = $crate::options::parse_value(
+                            stringify!($field),
+                            value,
+                            $crate::game_options!(@min $t $(, $min)?),
+                            $crate::game_options!(@max $t $(, $max)?),
+                        )?;
+                    }
+                )*
+                $crate::options::check_end(values)?;
+                Ok(options)
+            }
+
+            fn export(&self, str_buf: &mut $crate::StrBuf) -> $crate::Result<()> {
+                let mut writer = $crate::options::Writer::new(str_buf);
+                $( writer.value(&self.$field)?; )*
+                Ok(())
+            }
+
+            fn options_str() -> usize {
+                // Each value is followed by a space or the NUL byte.
+                let mut size = 0;
+                $(
+                    size += 1 + <$t as $crate::options::OptionValue>::max_len(
+                        $crate::game_options!(@min $t $(, $min)?),
+                        $crate::game_options!(@max $t $(, $max)?),
+                    );
+                )*
+                size.max(1)
+            }
+        }
+    };
+    (@min $t:ty) => { <$t as $crate::options::OptionValue>::MIN };
+    (@min $t:ty, $min:expr) => { $min };
+    (@max $t:ty) => { <$t as $crate::options::OptionValue>::MAX };
+    (@max $t:ty, $max:expr) => { $max };
+}
+
+/// Parses the option `name` from `string` and checks its range.
+#[doc(hidden)]
+pub fn parse_value<T: OptionValue>(name: &str, string: &str, min: T, max: T) -> Result<T> {
+    let value = T::parse_value(string).map_err(|e| {
+        Error::new_dynamic(
+            ErrorCode::InvalidOptions,
+            format!("invalid option {name} \"{string}\": {e}"),
+        )
+    })?;
+    check_range(name, value, min, max)
+        .map_err(|e| Error::new_dynamic(ErrorCode::InvalidOptions, e))?;
+    Ok(value)
+}
+
+/// Panics if the default `value` of the option `name` is out of range.
+#[doc(hidden)]
+pub fn check_default<T: OptionValue>(name: &str, value: T, min: T, max: T) {
+    if let Err(e) = check_range(name, value, min, max) {
+        panic!("invalid default: {e}");
+    }
+}
+
+fn check_range<T: OptionValue>(
+    name: &str,
+    value: T,
+    min: T,
+    max: T,
+) -> std::result::Result<(), String> {
+    if value < min || value > max {
+        return Err(format!(
+            "option {name} must be between {min} and {max}, got {value}"
+        ));
+    }
+    Ok(())
+}
+
+/// Returns an error if there are values left after parsing all options.
+#[doc(hidden)]
+pub fn check_end<'s>(mut values: impl Iterator<Item = &'s str>) -> Result<()> {
+    match values.next() {
+        Some(value) => Err(Error::new_dynamic(
+            ErrorCode::InvalidOptions,
+            format!("unexpected option \"{value}\""),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Writes space-separated values into a [`StrBuf`].
+#[doc(hidden)]
+pub struct Writer<'a, 'b> {
+    str_buf: &'a mut StrBuf<'b>,
+    first: bool,
+}
+
+impl<'a, 'b> Writer<'a, 'b> {
+    pub fn new(str_buf: &'a mut StrBuf<'b>) -> Self {
+        Self {
+            str_buf,
+            first: true,
+        }
+    }
+
+    pub fn value(&mut self, value: &impl Display) -> Result<()> {
+        let separator = if self.first { "" } else { " " };
+        self.first = false;
+        write!(self.str_buf, "{separator}{value}").map_err(|fmt::Error| {
+            Error::new_static(
+                ErrorCode::StateCorrupted,
+                "options exceed the options_str size\0",
+            )
+        })
+    }
+}
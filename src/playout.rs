@@ -0,0 +1,71 @@
+//! Random playouts running entirely in Rust.
+//!
+//! This avoids one FFI round trip per move when _surena_ engines request
+//! playouts via
+//! [`GameMethods::playout()`](crate::GameMethods::playout).
+
+use crate::{
+    ai::State, buf_sizer, player_id, ptr_vec::Storage, rng::Rng, sync_counter, Error, ErrorCode,
+    PtrVec, Result, PLAYER_RAND,
+};
+
+/// Default limit for the number of moves of playouts started from Rust.
+///
+/// Playouts requested via the C API are not limited.
+pub const MAX_MOVES: usize = 10_000;
+
+/// Plays uniformly random moves until the game is over or `max_moves` moves
+/// have been made.
+///
+/// Moves of [`PLAYER_RAND`] are drawn via
+/// [`State::random_move()`].
+/// If given, the winners are written into `winners` via
+/// [`State::winners()`].
+/// Returns whether the game is over.
+/// If the game is not over after `max_moves`, `false` is returned and no
+/// winners are written.
+/// `sizer` must be the [`buf_sizer`] returned by
+/// [`GameMethods::create()`](crate::GameMethods::create).
+pub fn random_playout<G: State>(
+    game: &mut G,
+    seed: u64,
+    sizer: &buf_sizer,
+    max_moves: usize,
+    sync_ctr: &mut sync_counter,
+    winners: Option<&mut PtrVec<player_id>>,
+) -> Result<bool> {
+    let mut rng = Rng::new(seed);
+    let mut players = Storage::new(sizer.max_players_to_move.into());
+    let mut moves = Storage::new(sizer.max_moves as usize);
+
+    let mut made = 0;
+    loop {
+        game.movers(&mut players.get_ptr_vec())?;
+        if players.is_empty() {
+            if let Some(winners) = winners {
+                game.winners(winners)?;
+            }
+            return Ok(true);
+        }
+
+        for &player in players.iter() {
+            if made >= max_moves {
+                return Ok(false);
+            }
+            let mov = if player == PLAYER_RAND {
+                game.random_move(rng.next_u64())?
+            } else {
+                game.moves(player, &mut moves.get_ptr_vec())?;
+                if moves.is_empty() {
+                    return Err(Error::new_static(
+                        ErrorCode::StateCorrupted,
+                        "player to move has no moves\0",
+                    ));
+                }
+                moves[rng.index(moves.len())]
+            };
+            game.play(player, mov, sync_ctr)?;
+            made += 1;
+        }
+    }
+}
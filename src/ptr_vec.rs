@@ -4,12 +4,12 @@
 //! buffer.
 
 use std::{
-    fmt::Write,
+    fmt::{self, Debug, Write},
     mem::{size_of, transmute, MaybeUninit},
     num::NonZeroU8,
-    ops::{Deref, DerefMut, Index, IndexMut},
+    ops::{Deref, DerefMut},
     os::raw::c_char,
-    ptr::NonNull,
+    ptr::{self, NonNull},
     slice,
     str::{from_utf8, Utf8Error},
 };
@@ -19,6 +19,25 @@ use std::{
 /// [`PtrVec`] allows to perform vector operations on memory not allocated by
 /// a [`Vec`].
 /// This is especially useful for buffers provided via FFI.
+///
+/// It dereferences to a slice of the initialized elements, which provides
+/// indexing, iteration, sorting, etc.
+///
+/// # Example
+/// ```
+/// # use surena_game::ptr_vec::Storage;
+/// let mut storage = Storage::new(8);
+/// let mut moves = storage.get_ptr_vec();
+/// moves.extend([4, 1, 3, 1, 4]);
+/// moves.sort_unstable();
+/// moves.dedup();
+/// moves.retain(|&m| m != 3);
+/// assert_eq!([1, 4], *moves);
+/// assert_eq!(Some(4), moves.pop());
+/// moves.insert(0, 2);
+/// assert_eq!("[2, 1]", format!("{moves:?}"));
+/// assert_eq!(3, moves.iter().sum::<i32>());
+/// ```
 pub struct PtrVec<'l, T> {
     buf: &'l mut [MaybeUninit<T>],
     len: &'l mut usize,
@@ -83,12 +102,143 @@ impl<'l, T> PtrVec<'l, T> {
             .write(value);
         *self.len += 1;
     }
+
+    /// Removes the last element and returns it or [`None`] if the vector is
+    /// empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        *self.len -= 1;
+        Some(unsafe { self.buf[self.len()].assume_init_read() })
+    }
+
+    /// Shortens the vector to `len` by dropping the remaining elements.
+    ///
+    /// Has no effect if `len` is not smaller than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            *self.len -= 1;
+            unsafe {
+                self.buf[self.len()].assume_init_drop();
+            }
+        }
+    }
+
+    /// Drops all elements.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Inserts `value` at `index` and shifts all following elements to the
+    /// right.
+    ///
+    /// # Panics
+    /// Panics if `index` is larger than the length or the vector is full.
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len();
+        assert!(index <= len, "insertion index out of PtrVec bounds");
+        assert!(!self.is_full(), "cannot insert into full PtrVec");
+
+        unsafe {
+            let ptr = self.buf.as_mut_ptr().add(index);
+            ptr::copy(ptr, ptr.add(1), len - index);
+        }
+        self.buf[index].write(value);
+        *self.len += 1;
+    }
+
+    /// Removes and returns the element at `index` and shifts all following
+    /// elements to the left.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "removal index out of PtrVec bounds");
+
+        *self.len -= 1;
+        unsafe {
+            let ptr = self.buf.as_mut_ptr().add(index);
+            let value = ptr.read().assume_init();
+            ptr::copy(ptr.add(1), ptr, len - index - 1);
+            value
+        }
+    }
+
+    /// Removes and returns the element at `index` and replaces it with the
+    /// last element.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len(), "removal index out of PtrVec bounds");
+
+        let last = self.len() - 1;
+        self.buf.swap(index, last);
+        self.pop().expect("vector is not empty")
+    }
+
+    /// Keeps only the elements for which `f` returns `true`.
+    ///
+    /// The order of the kept elements is preserved.
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let len = self.len();
+        // Elements are leaked instead of dropped twice if `f` panics.
+        *self.len = 0;
+
+        let mut kept = 0;
+        for i in 0..len {
+            if f(unsafe { self.buf[i].assume_init_ref() }) {
+                self.buf.swap(kept, i);
+                kept += 1;
+            } else {
+                unsafe { self.buf[i].assume_init_drop() };
+            }
+        }
+        *self.len = kept;
+    }
+
+    /// Removes consecutive elements for which `same_bucket(a, b)` returns
+    /// `true`, where `b` is the preceding element which is kept.
+    pub fn dedup_by(&mut self, mut same_bucket: impl FnMut(&mut T, &mut T) -> bool) {
+        let len = self.len();
+        if len <= 1 {
+            return;
+        }
+        // Elements are leaked instead of dropped twice if `same_bucket`
+        // panics.
+        *self.len = 0;
+
+        let mut kept = 1;
+        for i in 1..len {
+            let (head, tail) = self.buf.split_at_mut(i);
+            let (a, b) = unsafe { (tail[0].assume_init_mut(), head[kept - 1].assume_init_mut()) };
+            if same_bucket(a, b) {
+                unsafe { self.buf[i].assume_init_drop() };
+            } else {
+                self.buf.swap(kept, i);
+                kept += 1;
+            }
+        }
+        *self.len = kept;
+    }
+}
+
+impl<'l, T: PartialEq> PtrVec<'l, T> {
+    /// Removes consecutive duplicates.
+    #[inline]
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b);
+    }
 }
 
 impl<'l, T: Clone> PtrVec<'l, T> {
     /// Resizes the vector to `new_len`.
     ///
-    /// If `new_len` is smaller than the current length, the vector is extended
+    /// If `new_len` is larger than the current length, the vector is extended
     /// with clones of `value`.
     ///
     /// # Panics
@@ -100,12 +250,7 @@ impl<'l, T: Clone> PtrVec<'l, T> {
         );
 
         if new_len <= self.len() {
-            for _ in new_len..self.len() {
-                *self.len -= 1;
-                unsafe {
-                    self.buf[self.len()].assume_init_drop();
-                }
-            }
+            self.truncate(new_len);
         } else {
             for _ in self.len()..new_len {
                 self.push(value.clone());
@@ -129,29 +274,73 @@ impl<'l, T: Clone> PtrVec<'l, T> {
     }
 }
 
-const ACCESS_ERROR: &str = "cannot access element outside PtrVec";
+impl<'l, T> Deref for PtrVec<'l, T> {
+    type Target = [T];
 
-impl<'l, T> Index<usize> for PtrVec<'l, T> {
-    type Output = T;
+    /// Returns a slice over the initialized elements.
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { transmute::<&[MaybeUninit<T>], &[T]>(&self.buf[..self.len()]) }
+    }
+}
 
+impl<'l, T> DerefMut for PtrVec<'l, T> {
+    /// Returns a slice over the initialized elements.
     #[inline]
-    fn index(&self, index: usize) -> &Self::Output {
-        unsafe { self.buf.get(index).expect(ACCESS_ERROR).assume_init_ref() }
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let len = self.len();
+        unsafe { transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.buf[..len]) }
     }
 }
 
-impl<'l, T> IndexMut<usize> for PtrVec<'l, T> {
+impl<'a, 'l, T> IntoIterator for &'a PtrVec<'l, T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
     #[inline]
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        unsafe {
-            self.buf
-                .get_mut(index)
-                .expect(ACCESS_ERROR)
-                .assume_init_mut()
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'l, T> IntoIterator for &'a mut PtrVec<'l, T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'l, T> Extend<T> for PtrVec<'l, T> {
+    /// Pushes all elements of `iter`.
+    ///
+    /// # Panics
+    /// Panics if the vector becomes full.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
         }
     }
 }
 
+impl<'a, 'l, T: Copy + 'a> Extend<&'a T> for PtrVec<'l, T> {
+    /// Pushes copies of all elements of `iter`.
+    ///
+    /// # Panics
+    /// Panics if the vector becomes full.
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<'l, T: Debug> Debug for PtrVec<'l, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
 impl<'l> PtrVec<'l, NonZeroU8> {
     /// Size must be at least 1.
     #[inline]
@@ -180,7 +369,7 @@ impl<'l> Write for PtrVec<'l, NonZeroU8> {
     /// # let mut ptr_vec = storage.get_ptr_vec();
     /// write!(ptr_vec, "example string").expect("failed to write PtrVec");
     /// ```
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
         for b in s.bytes() {
             let b = NonZeroU8::new(b).ok_or_else(Default::default)?;
             if self.is_full() {
@@ -225,7 +414,7 @@ impl<T> Storage<T> {
     /// The capacity of the new [`PtrVec`] is equal to the capacity of `self`.
     /// The internal storage will be reset.
     #[inline]
-    pub fn get_ptr_vec(&mut self) -> PtrVec<T> {
+    pub fn get_ptr_vec(&mut self) -> PtrVec<'_, T> {
         self.clear();
         PtrVec {
             buf: &mut self.buf,
@@ -233,6 +422,33 @@ impl<T> Storage<T> {
         }
     }
 
+    /// Length of the underlying memory.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns a pointer to the underlying memory for passing it via FFI.
+    ///
+    /// The internal storage will be reset.
+    #[inline]
+    pub(crate) fn get_raw_ptr(&mut self) -> *mut T {
+        self.clear();
+        self.buf.as_mut_ptr().cast()
+    }
+
+    /// Sets the number of initialized items after writing via
+    /// [`Self::get_raw_ptr()`].
+    ///
+    /// # Safety
+    /// The first `len` items must be initialized and `len` must not exceed the
+    /// capacity.
+    #[inline]
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.capacity());
+        self.len = len;
+    }
+
     fn clear(&mut self) {
         unsafe {
             while self.len > 0 {
@@ -284,3 +500,161 @@ impl Storage<NonZeroU8> {
         from_utf8(unsafe { transmute::<&[NonZeroU8], &[u8]>(self) })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        panic::{catch_unwind, AssertUnwindSafe},
+        rc::Rc,
+    };
+
+    /// Returns counted elements, whose strong counts show which of their
+    /// clones are still alive.
+    fn counted(values: &[u8]) -> Vec<Rc<u8>> {
+        values.iter().copied().map(Rc::new).collect()
+    }
+
+    fn counts(values: &[Rc<u8>]) -> Vec<usize> {
+        values.iter().map(Rc::strong_count).collect()
+    }
+
+    fn numbers(values: &[Rc<u8>]) -> Vec<u8> {
+        values.iter().map(|v| **v).collect()
+    }
+
+    #[test]
+    fn insert() {
+        let values = counted(&[0, 1, 2, 3]);
+        let mut storage = Storage::new(4);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend([&values[0], &values[2]].map(Rc::clone));
+        vec.insert(1, values[1].clone());
+        vec.insert(3, values[3].clone());
+        assert_eq!([0, 1, 2, 3], numbers(&vec)[..]);
+        assert_eq!([2; 4], counts(&values)[..]);
+        drop(storage);
+        assert_eq!([1; 4], counts(&values)[..]);
+    }
+
+    #[test]
+    fn remove() {
+        let values = counted(&[0, 1, 2, 3]);
+        let mut storage = Storage::new(4);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        assert_eq!(1, *vec.remove(1));
+        assert_eq!(3, *vec.remove(2));
+        assert_eq!([0, 2], numbers(&vec)[..]);
+        assert_eq!([2, 1, 2, 1], counts(&values)[..]);
+        drop(storage);
+        assert_eq!([1; 4], counts(&values)[..]);
+    }
+
+    #[test]
+    fn swap_remove() {
+        let values = counted(&[0, 1, 2, 3]);
+        let mut storage = Storage::new(4);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        assert_eq!(0, *vec.swap_remove(0));
+        assert_eq!(2, *vec.swap_remove(2));
+        assert_eq!([3, 1], numbers(&vec)[..]);
+        assert_eq!([1, 2, 1, 2], counts(&values)[..]);
+        drop(storage);
+        assert_eq!([1; 4], counts(&values)[..]);
+    }
+
+    #[test]
+    fn retain() {
+        let values = counted(&[0, 1, 2, 3, 4, 5]);
+        let mut storage = Storage::new(6);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        vec.retain(|v| **v % 2 == 0);
+        assert_eq!([0, 2, 4], numbers(&vec)[..]);
+        assert_eq!([2, 1, 2, 1, 2, 1], counts(&values)[..]);
+        drop(storage);
+        assert_eq!([1; 6], counts(&values)[..]);
+    }
+
+    #[test]
+    fn retain_leaks_on_panic() {
+        let values = counted(&[0, 1, 2, 3, 4, 5]);
+        let mut storage = Storage::new(6);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            vec.retain(|v| {
+                assert_ne!(3, **v);
+                **v % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
+        assert!(vec.is_empty());
+        drop(storage);
+        // Only the rejected element was dropped, the others are leaked.
+        assert_eq!([2, 1, 2, 2, 2, 2], counts(&values)[..]);
+    }
+
+    #[test]
+    fn dedup() {
+        let values = counted(&[1, 1, 2, 2, 2, 3]);
+        let mut storage = Storage::new(6);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        vec.dedup();
+        assert_eq!([1, 2, 3], numbers(&vec)[..]);
+        // The first element of each run is kept.
+        assert_eq!([2, 1, 2, 1, 1, 2], counts(&values)[..]);
+        drop(storage);
+        assert_eq!([1; 6], counts(&values)[..]);
+    }
+
+    #[test]
+    fn dedup_by_leaks_on_panic() {
+        let values = counted(&[1, 1, 2, 2, 2, 3]);
+        let mut storage = Storage::new(6);
+        let mut vec = storage.get_ptr_vec();
+        vec.extend(values.iter().cloned());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            vec.dedup_by(|a, b| {
+                assert_ne!(3, **a);
+                a == b
+            })
+        }));
+        assert!(result.is_err());
+        assert!(vec.is_empty());
+        drop(storage);
+        // Only the duplicates were dropped, the others are leaked.
+        assert_eq!([2, 1, 2, 1, 1, 2], counts(&values)[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot insert into full PtrVec")]
+    fn insert_into_full() {
+        let mut storage = Storage::new(1);
+        let mut vec = storage.get_ptr_vec();
+        vec.push(0);
+        vec.insert(0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index out of PtrVec bounds")]
+    fn insert_out_of_bounds() {
+        let mut storage = Storage::new(2);
+        let mut vec = storage.get_ptr_vec();
+        vec.push(0);
+        vec.insert(2, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index out of PtrVec bounds")]
+    fn remove_out_of_bounds() {
+        let mut storage = Storage::new(2);
+        let mut vec = storage.get_ptr_vec();
+        vec.push(0);
+        vec.remove(1);
+    }
+}
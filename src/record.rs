@@ -0,0 +1,321 @@
+//! Text records of played matches.
+//!
+//! A record consists of one `key value` pair per line:
+//! ```text
+//! game Nim
+//! variant Standard
+//! impl surena_game_rs
+//! options 21 3
+//! state A 21
+//! move 1 3
+//! move 2 1
+//! ```
+//! `game`, `variant`, and `impl` are the names from the [`Metadata`].
+//! `options` is written by [`GameMethods::export_options`] and only present
+//! with the [`game_feature_flags::options`](crate::game_feature_flags::options).
+//! `state` is the initial state written by [`GameMethods::export_state`].
+//! Each `move` line contains the player and the move string written by
+//! [`GameMethods::get_move_str`].
+//! Empty lines and lines starting with `#` are ignored.
+//!
+//! A [`Recorder`] creates a [`Record`] while playing and
+//! [`Record::replay()`] plays it back.
+//!
+//! # Example
+//! ```
+//! # use surena_game::record::Record;
+//! let text = "game Nim\nvariant Standard\nimpl surena_game_rs\nmove 1 3\n";
+//! let record: Record = text.parse().unwrap();
+//! assert_eq!(vec![(1, "3".to_string())], record.moves);
+//! assert_eq!(text, record.to_string());
+//! ```
+//!
+//! ```ignore
+//! let (mut game, sizer) = MyGame::create(&GameInit::Default)?;
+//! let mut recorder = Recorder::new(&metadata(), &mut game, &sizer)?;
+//! recorder.make_move(&mut game, player, mov, &mut sync_ctr)?;
+//! std::fs::write("match.txt", recorder.into_record().to_string())?;
+//!
+//! let record: Record = std::fs::read_to_string("match.txt")?.parse()?;
+//! let (replayed, _) = record.replay::<MyGame>(&metadata())?;
+//! ```
+
+use crate::{
+    buf_sizer, describe, move_code, player_id, ptr_vec::Storage, sync_counter, Error, ErrorCode,
+    GameInit, GameMethods, Metadata, Result, ValidCStr, SYNC_COUNTER_DEFAULT,
+};
+
+use std::{ffi::CStr, fmt, num::NonZeroU8, os::raw::c_char, str::FromStr};
+
+/// A played match.
+///
+/// Values must not contain line breaks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub game: String,
+    pub variant: String,
+    pub implementation: String,
+    pub options: Option<String>,
+    /// The initial state or [`None`] for the default one.
+    pub state: Option<String>,
+    /// Players and move strings in the order of play.
+    pub moves: Vec<(player_id, String)>,
+}
+
+impl Record {
+    /// Creates game `G` from this record and makes all recorded moves.
+    ///
+    /// Fails if the names do not match `metadata` or any move is illegal
+    /// according to [`GameMethods::is_legal_move`].
+    pub fn replay<G: GameMethods>(&self, metadata: &Metadata) -> Result<(G, buf_sizer)> {
+        let names = [
+            ("game", &self.game, metadata.game_name),
+            ("variant", &self.variant, metadata.variant_name),
+            ("impl", &self.implementation, metadata.impl_name),
+        ];
+        for (key, recorded, expected) in names {
+            let expected = to_str(expected);
+            if recorded != &expected {
+                return Err(Error::new_dynamic(
+                    ErrorCode::InvalidInput,
+                    format!("record has {key} {recorded} but {expected} is required"),
+                ));
+            }
+        }
+
+        let init_info = if self.options.is_none() && self.state.is_none() {
+            GameInit::Default
+        } else {
+            GameInit::Standard {
+                opts: self.options.as_deref(),
+                legacy: None,
+                state: self.state.as_deref(),
+            }
+        };
+        let (mut game, sizer) = G::create(&init_info)?;
+
+        let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+        for (i, (player, string)) in self.moves.iter().enumerate() {
+            let context = |error: Error| {
+                Error::new_dynamic(
+                    error.code,
+                    format!("move {} ({player} {string}): {}", i + 1, describe(&error)),
+                )
+            };
+            let mov = game.get_move_code(*player, string).map_err(context)?;
+            game.is_legal_move(*player, mov, sync_ctr)
+                .map_err(context)?;
+            game.make_move(*player, mov, &mut sync_ctr)
+                .map_err(context)?;
+        }
+        Ok((game, sizer))
+    }
+}
+
+impl fmt::Display for Record {
+    /// Writes the record in the text format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "game {}", self.game)?;
+        writeln!(f, "variant {}", self.variant)?;
+        writeln!(f, "impl {}", self.implementation)?;
+        if let Some(options) = &self.options {
+            writeln!(f, "options {options}")?;
+        }
+        if let Some(state) = &self.state {
+            writeln!(f, "state {state}")?;
+        }
+        for (player, mov) in &self.moves {
+            writeln!(f, "move {player} {mov}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Record {
+    type Err = Error;
+
+    /// Parses the text format.
+    fn from_str(text: &str) -> Result<Self> {
+        let mut record = Record::default();
+        let (mut game, mut variant, mut implementation) = (None, None, None);
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| {
+                Error::new_dynamic(
+                    ErrorCode::InvalidInput,
+                    format!("line {}: {message}", i + 1),
+                )
+            };
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let duplicate = match key {
+                "game" => game.replace(value).is_some(),
+                "variant" => variant.replace(value).is_some(),
+                "impl" => implementation.replace(value).is_some(),
+                "options" => record.options.replace(value.to_string()).is_some(),
+                "state" => record.state.replace(value.to_string()).is_some(),
+                "move" => {
+                    let (player, mov) = value
+                        .split_once(' ')
+                        .ok_or_else(|| error("expected player and move"))?;
+                    let player = player.parse().map_err(|_| error("invalid player"))?;
+                    record.moves.push((player, mov.to_string()));
+                    false
+                }
+                _ => return Err(error(&format!("unknown key {key}"))),
+            };
+            if duplicate {
+                return Err(error(&format!("duplicate key {key}")));
+            }
+            if key != "move" && !record.moves.is_empty() {
+                return Err(error(&format!("{key} after the first move")));
+            }
+        }
+
+        let missing =
+            |key: &str| Error::new_dynamic(ErrorCode::InvalidInput, format!("missing key {key}"));
+        record.game = game.ok_or_else(|| missing("game"))?.to_string();
+        record.variant = variant.ok_or_else(|| missing("variant"))?.to_string();
+        record.implementation = implementation.ok_or_else(|| missing("impl"))?.to_string();
+        Ok(record)
+    }
+}
+
+/// Creates a [`Record`] while playing a match.
+pub struct Recorder {
+    record: Record,
+    str_buf: Storage<NonZeroU8>,
+}
+
+impl Recorder {
+    /// Starts recording from the current state of `game`.
+    ///
+    /// `sizer` must be the [`buf_sizer`] returned by [`GameMethods::create`].
+    pub fn new<G: GameMethods>(
+        metadata: &Metadata,
+        game: &mut G,
+        sizer: &buf_sizer,
+    ) -> Result<Self> {
+        let capacity = sizer.options_str.max(sizer.state_str).max(sizer.move_str);
+        let mut recorder = Self {
+            record: Record {
+                game: to_str(metadata.game_name),
+                variant: to_str(metadata.variant_name),
+                implementation: to_str(metadata.impl_name),
+                ..Default::default()
+            },
+            str_buf: Storage::new(capacity),
+        };
+        if metadata.features.options() {
+            game.export_options(&mut recorder.str_buf.get_ptr_vec())?;
+            recorder.record.options = Some(recorder.string()?);
+        }
+        game.export_state(&mut recorder.str_buf.get_ptr_vec())?;
+        recorder.record.state = Some(recorder.string()?);
+        Ok(recorder)
+    }
+
+    /// Records the move and makes it in `game`.
+    pub fn make_move<G: GameMethods>(
+        &mut self,
+        game: &mut G,
+        player: player_id,
+        mov: move_code,
+        sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
+        game.get_move_str(player, mov, &mut self.str_buf.get_ptr_vec())?;
+        let string = self.string()?;
+        game.make_move(player, mov, sync_ctr)?;
+        self.record.moves.push((player, string));
+        Ok(())
+    }
+
+    /// The record so far.
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+
+    /// Finishes recording.
+    pub fn into_record(self) -> Record {
+        self.record
+    }
+
+    /// Returns the content of the string buffer.
+    fn string(&self) -> Result<String> {
+        let string = self.str_buf.as_str().map_err(|e| {
+            Error::new_dynamic(
+                ErrorCode::StateCorrupted,
+                format!("game wrote invalid UTF-8: {e}"),
+            )
+        })?;
+        if string.contains(['\n', '\r']) {
+            return Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "records cannot contain line breaks\0",
+            ));
+        }
+        Ok(string.to_string())
+    }
+}
+
+fn to_str(string: ValidCStr<'static>) -> String {
+    let string: *const c_char = string.into();
+    unsafe { CStr::from_ptr(string) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "game Nim\nvariant Standard\nimpl surena_game_rs\n";
+
+    #[test]
+    fn text_round_trip() {
+        let record = Record {
+            game: "Nim".to_string(),
+            variant: "Standard".to_string(),
+            implementation: "surena_game_rs".to_string(),
+            options: Some("21 3".to_string()),
+            state: Some("A 21".to_string()),
+            moves: vec![(1, "3".to_string()), (2, "1".to_string())],
+        };
+        let text = record.to_string();
+        assert_eq!(
+            format!("{HEADER}options 21 3\nstate A 21\nmove 1 3\nmove 2 1\n"),
+            text
+        );
+        assert_eq!(record, text.parse::<Record>().unwrap());
+    }
+
+    #[test]
+    fn parse_skips_comments_and_empty_lines() {
+        let text = format!("# comment\n\n{HEADER}\r\nmove 1 e2 e4\r\n");
+        let record: Record = text.parse().unwrap();
+        assert_eq!("surena_game_rs", record.implementation);
+        assert_eq!(None, record.options);
+        assert_eq!(None, record.state);
+        // Move strings may contain spaces.
+        assert_eq!(vec![(1, "e2 e4".to_string())], record.moves);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_records() {
+        assert!(format!("{HEADER}move 1 3\n").parse::<Record>().is_ok());
+        let invalid = [
+            format!("{HEADER}game Nim\n"),
+            format!("{HEADER}move 1 3\nstate A 5\n"),
+            format!("{HEADER}move 1\n"),
+            format!("{HEADER}move A 3\n"),
+            format!("{HEADER}winner 1\n"),
+            "game Nim\nvariant Standard\n".to_string(),
+        ];
+        for text in invalid {
+            assert!(text.parse::<Record>().is_err(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Helpers for binary serialization of game states.
+//!
+//! [`GameMethods::serialize()`](crate::GameMethods::serialize) writes the
+//! state via [`Serialize`] and
+//! [`GameMethods::create()`](crate::GameMethods::create) can restore it from
+//! [`GameInit::Serialized`](crate::GameInit::Serialized) via [`deserialize`].
+//! All integers are encoded as fixed-width little-endian values.
+//!
+//! # Example
+//! ```
+//! # use surena_game::{ptr_vec::Storage, serialize::*};
+//! let mut storage = Storage::new(3);
+//! (42u16, true).serialize(&mut storage.get_ptr_vec()).unwrap();
+//! let (counter, turn): (u16, bool) = deserialize(&storage).unwrap();
+//! assert_eq!(42, counter);
+//! assert!(turn);
+//! ```
+
+use crate::{Error, ErrorCode, PtrVec, Result};
+
+/// Types which can be written into a serialization buffer.
+pub trait Serialize {
+    /// Appends the encoding of `self` to `buf`.
+    ///
+    /// Fails if `buf` is too small.
+    fn serialize(&self, buf: &mut PtrVec<u8>) -> Result<()>;
+}
+
+/// Types which can be read back from the output of [`Serialize`].
+pub trait Deserialize: Sized {
+    /// Reads a value from the front of `reader`.
+    fn deserialize(reader: &mut Reader) -> Result<Self>;
+}
+
+/// Deserializes `bytes` completely into a `T`.
+///
+/// Fails if `bytes` is too short or contains trailing data.
+pub fn deserialize<T: Deserialize>(bytes: &[u8]) -> Result<T> {
+    let mut reader = Reader::new(bytes);
+    let value = T::deserialize(&mut reader)?;
+    reader.finish()?;
+    Ok(value)
+}
+
+/// Cursor over serialized data.
+pub struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    /// Create a [`Reader`] starting at the beginning of `bytes`.
+    #[inline]
+    pub fn new(bytes: &'b [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Consumes the next `len` bytes.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'b [u8]> {
+        if len > self.bytes.len() {
+            return Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "serialized data is too short\0",
+            ));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Returns an error if there are unread bytes left.
+    pub fn finish(self) -> Result<()> {
+        if !self.bytes.is_empty() {
+            return Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "trailing bytes in serialized data\0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Appends `bytes` to `buf` if there is enough space left.
+pub fn write_bytes(buf: &mut PtrVec<u8>, bytes: &[u8]) -> Result<()> {
+    if bytes.len() > buf.capacity() - buf.len() {
+        return Err(Error::new_static(
+            ErrorCode::OutOfMemory,
+            "serialization buffer is too small\0",
+        ));
+    }
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+macro_rules! impl_int {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl Serialize for $t {
+                #[inline]
+                fn serialize(&self, buf: &mut PtrVec<u8>) -> Result<()> {
+                    write_bytes(buf, &self.to_le_bytes())
+                }
+            }
+
+            impl Deserialize for $t {
+                #[inline]
+                fn deserialize(reader: &mut Reader) -> Result<Self> {
+                    let bytes = reader.read_bytes(std::mem::size_of::<$t>())?;
+                    Ok(<$t>::from_le_bytes(
+                        bytes.try_into().expect("read wrong number of bytes"),
+                    ))
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl Serialize for bool {
+    #[inline]
+    fn serialize(&self, buf: &mut PtrVec<u8>) -> Result<()> {
+        u8::from(*self).serialize(buf)
+    }
+}
+
+impl Deserialize for bool {
+    fn deserialize(reader: &mut Reader) -> Result<Self> {
+        match u8::deserialize(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::new_static(
+                ErrorCode::InvalidInput,
+                "invalid serialized boolean\0",
+            )),
+        }
+    }
+}
+
+macro_rules! impl_tuple {
+    ( $( $t:ident $i:tt ),* ) => {
+        impl<$( $t: Serialize ),*> Serialize for ($( $t, )*) {
+            fn serialize(&self, buf: &mut PtrVec<u8>) -> Result<()> {
+                $( self.$i.serialize(buf)?; )*
+                Ok(())
+            }
+        }
+
+        impl<$( $t: Deserialize ),*> Deserialize for ($( $t, )*) {
+            fn deserialize(reader: &mut Reader) -> Result<Self> {
+                Ok(($( $t::deserialize(reader)?, )*))
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
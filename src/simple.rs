@@ -0,0 +1,466 @@
+//! Higher-level [`SimpleGame`] trait for games without advanced features.
+//!
+//! A [`SimpleGame`] is exported by wrapping it in [`Simple`], which implements
+//! [`GameMethods`] for
+//! [`create_game_methods()`](crate::create_game_methods).
+//! Instead of filling [`PtrVec`]s, methods simply return [`Vec`]s and
+//! [`String`]s, which are checked against the declared [`Limits`].
+//! `copy_from`, `is_legal_move`, and `get_move_code` are derived from the
+//! other methods.
+//! Optional methods like [`SimpleGame::get_id`] are forwarded and must be
+//! implemented when their feature flag is enabled.
+//!
+//! # Example
+//! ```
+//! use surena_game::{simple::*, testing, *};
+//!
+//! /// The player who takes the last coin wins.
+//! #[derive(Clone, PartialEq, Eq)]
+//! struct Coins {
+//!     coins: u8,
+//!     turn: player_id,
+//! }
+//!
+//! impl SimpleGame for Coins {
+//!     fn create(_init_info: &GameInit) -> Result<Self> {
+//!         Ok(Self { coins: 5, turn: 1 })
+//!     }
+//!
+//!     fn limits(&self) -> Limits {
+//!         Limits {
+//!             player_count: 2,
+//!             max_players_to_move: 1,
+//!             max_moves: 2,
+//!             max_results: 1,
+//!             state_str: 3,
+//!             move_str: 1,
+//!             ..Default::default()
+//!         }
+//!     }
+//!
+//!     fn import_state(&mut self, state: Option<&str>) -> Result<()> {
+//!         let (coins, turn) = state.unwrap_or("5 1").split_once(' ').unwrap_or_default();
+//!         match (coins.parse(), turn.parse()) {
+//!             (Ok(coins @ 0..=5), Ok(turn @ 1..=2)) => {
+//!                 *self = Self { coins, turn };
+//!                 Ok(())
+//!             }
+//!             _ => Err(Error::new_static(ErrorCode::InvalidState, "invalid state\0")),
+//!         }
+//!     }
+//!
+//!     fn export_state(&self) -> Result<String> {
+//!         Ok(format!("{} {}", self.coins, self.turn))
+//!     }
+//!
+//!     fn players_to_move(&self) -> Result<Vec<player_id>> {
+//!         Ok(if self.coins > 0 { vec![self.turn] } else { vec![] })
+//!     }
+//!
+//!     fn get_concrete_moves(&self, player: player_id) -> Result<Vec<move_code>> {
+//!         if player != self.turn || self.coins == 0 {
+//!             return Ok(vec![]);
+//!         }
+//!         Ok((1..=self.coins.min(2)).map(Into::into).collect())
+//!     }
+//!
+//!     fn get_move_str(&self, _player: player_id, mov: move_code) -> Result<String> {
+//!         Ok(mov.to_string())
+//!     }
+//!
+//!     fn make_move(&mut self, _player: player_id, mov: move_code) -> Result<()> {
+//!         self.coins -= mov as u8;
+//!         self.turn = 3 - self.turn;
+//!         Ok(())
+//!     }
+//!
+//!     fn get_results(&self) -> Result<Vec<player_id>> {
+//!         Ok(if self.coins == 0 { vec![3 - self.turn] } else { vec![] })
+//!     }
+//!
+//!     fn get_id(&self) -> Result<u64> {
+//!         Ok(u64::from(self.coins) << 8 | u64::from(self.turn))
+//!     }
+//! }
+//!
+//! let mut features = game_feature_flags::default();
+//! features.set_id(true);
+//! let metadata = Metadata {
+//!     game_name: cstr("Coins\0"),
+//!     variant_name: cstr("Standard\0"),
+//!     impl_name: cstr("surena_game_rs\0"),
+//!     version: semver { major: 0, minor: 1, patch: 0 },
+//!     features,
+//! };
+//! let violations = testing::check::<Simple<Coins>>(
+//!     metadata,
+//!     &GameInit::Default,
+//!     &testing::Config::default(),
+//! );
+//! assert!(violations.is_empty());
+//!
+//! let (mut game, _) = Simple::<Coins>::create(&GameInit::Default).unwrap();
+//! assert_eq!(5 << 8 | 1, game.get_id().unwrap());
+//! ```
+
+use crate::{
+    buf_sizer, move_code, player_id, sync_counter, Error, ErrorCode, GameInit, GameMethods, PtrVec,
+    Result, StrBuf,
+};
+
+use std::fmt::Write;
+
+/// Maxima declared by a [`SimpleGame`].
+///
+/// String lengths are in bytes and exclude the trailing NUL byte.
+/// These are used to compute the [`buf_sizer`] and outputs exceeding them are
+/// reported as [`ErrorCode::StateCorrupted`].
+/// Legacy and actions are not supported, so
+/// [`buf_sizer::legacy_str`] and [`buf_sizer::max_actions`] are left at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub player_count: u8,
+    pub max_players_to_move: u8,
+    pub max_moves: u32,
+    pub max_results: u8,
+    pub options_str: usize,
+    pub state_str: usize,
+    pub move_str: usize,
+    pub print_str: usize,
+    /// Maximum length of [`SimpleGame::serialize`] in bytes.
+    pub serialize: usize,
+}
+
+impl Limits {
+    /// Computes the [`buf_sizer`] for these limits.
+    pub fn sizer(&self) -> buf_sizer {
+        buf_sizer {
+            options_str: self.options_str + 1,
+            state_str: self.state_str + 1,
+            player_count: self.player_count,
+            max_players_to_move: self.max_players_to_move,
+            max_moves: self.max_moves,
+            max_results: self.max_results,
+            move_str: self.move_str + 1,
+            print_str: self.print_str + 1,
+            serialize: self.serialize,
+            ..Default::default()
+        }
+    }
+}
+
+/// Simplified alternative to [`GameMethods`].
+///
+/// Simultaneous moves are not supported, so the sync counter is not exposed.
+/// `clone` and `copy_from` are handled by the [`Clone`] implementation and
+/// `compare` by [`Eq`].
+/// Export it by wrapping it in [`Simple`].
+/// Hidden information, actions, legacy, and custom playouts are not
+/// supported.
+pub trait SimpleGame: Sized + Clone + Eq + Send {
+    fn create(init_info: &GameInit) -> Result<Self>;
+    /// Returns the maxima of this game.
+    ///
+    /// These must not change after [`Self::create`].
+    fn limits(&self) -> Limits;
+    fn import_state(&mut self, state: Option<&str>) -> Result<()>;
+    fn export_state(&self) -> Result<String>;
+    fn players_to_move(&self) -> Result<Vec<player_id>>;
+    fn get_concrete_moves(&self, player: player_id) -> Result<Vec<move_code>>;
+    fn get_move_str(&self, player: player_id, mov: move_code) -> Result<String>;
+    fn make_move(&mut self, player: player_id, mov: move_code) -> Result<()>;
+    fn get_results(&self) -> Result<Vec<player_id>>;
+
+    /// Accepts all moves returned by [`Self::get_concrete_moves`] for players
+    /// returned by [`Self::players_to_move`].
+    #[allow(clippy::wrong_self_convention)]
+    fn is_legal_move(&self, player: player_id, mov: move_code) -> Result<()> {
+        if !self.players_to_move()?.contains(&player) {
+            return Err(Error::new_static(
+                ErrorCode::InvalidPlayer,
+                "this player is not to move\0",
+            ));
+        }
+        if !self.get_concrete_moves(player)?.contains(&mov) {
+            return Err(Error::new_static(ErrorCode::InvalidMove, "illegal move\0"));
+        }
+        Ok(())
+    }
+
+    /// Searches the move among [`Self::get_concrete_moves`] whose
+    /// [`Self::get_move_str`] equals `string`.
+    fn get_move_code(&self, player: player_id, string: &str) -> Result<move_code> {
+        for mov in self.get_concrete_moves(player)? {
+            if self.get_move_str(player, mov)? == string {
+                return Ok(mov);
+            }
+        }
+        Err(Error::new_dynamic(
+            ErrorCode::InvalidInput,
+            format!("unknown move \"{string}\""),
+        ))
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::options`](crate::game_feature_flags::options) is
+    /// enabled.
+    fn export_options(&self) -> Result<String> {
+        unimplemented!("export_options")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::print`](crate::game_feature_flags::print) is
+    /// enabled.
+    fn print(&self) -> Result<String> {
+        unimplemented!("print")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::serializable`](crate::game_feature_flags::serializable)
+    /// is enabled.
+    ///
+    /// The data must be accepted by [`Self::create`] as
+    /// [`GameInit::Serialized`].
+    fn serialize(&self) -> Result<Vec<u8>> {
+        unimplemented!("serialize")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::random_moves`](crate::game_feature_flags::random_moves)
+    /// is enabled.
+    ///
+    /// See [`GameMethods::get_concrete_move_probabilities`].
+    #[allow(unused_variables)]
+    fn get_concrete_move_probabilities(&self, player: player_id) -> Result<Vec<f32>> {
+        unimplemented!("get_concrete_move_probabilities")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::random_moves`](crate::game_feature_flags::random_moves)
+    /// is enabled.
+    ///
+    /// See [`GameMethods::get_random_move`].
+    #[allow(unused_variables)]
+    fn get_random_move(&self, seed: u64) -> Result<move_code> {
+        unimplemented!("get_random_move")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::random_moves`](crate::game_feature_flags::random_moves)
+    /// is enabled.
+    ///
+    /// See [`GameMethods::discretize`].
+    #[allow(unused_variables)]
+    fn discretize(&mut self, seed: u64) -> Result<()> {
+        unimplemented!("discretize")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::move_ordering`](crate::game_feature_flags::move_ordering)
+    /// is enabled.
+    ///
+    /// See [`GameMethods::get_move_ordering`].
+    #[allow(unused_variables)]
+    fn get_move_ordering(&self, player: player_id) -> Result<Vec<move_code>> {
+        unimplemented!("get_move_ordering")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::scores`](crate::game_feature_flags::scores) is
+    /// enabled.
+    ///
+    /// The score at index `i` belongs to player `i + 1`.
+    fn get_scores(&self) -> Result<Vec<i32>> {
+        unimplemented!("get_scores")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::id`](crate::game_feature_flags::id) is enabled.
+    ///
+    /// See [`GameMethods::get_id`].
+    fn get_id(&self) -> Result<u64> {
+        unimplemented!("get_id")
+    }
+
+    /// Must be implemented when the
+    /// [`game_feature_flags::eval`](crate::game_feature_flags::eval) is
+    /// enabled.
+    ///
+    /// See [`GameMethods::get_eval`].
+    #[allow(unused_variables)]
+    fn get_eval(&self, player: player_id) -> Result<f32> {
+        unimplemented!("get_eval")
+    }
+}
+
+/// Adapter implementing [`GameMethods`] for a [`SimpleGame`].
+///
+/// # Example
+/// ```ignore
+/// create_game_methods::<Simple<MyGame>>(metadata);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Simple<G>(pub G);
+
+impl<G: SimpleGame> GameMethods for Simple<G> {
+    fn create(init_info: &GameInit) -> Result<(Self, buf_sizer)> {
+        let game = G::create(init_info)?;
+        let sizer = game.limits().sizer();
+        Ok((Self(game), sizer))
+    }
+
+    fn copy_from(&mut self, other: &mut Self) -> Result<()> {
+        self.0.clone_from(&other.0);
+        Ok(())
+    }
+
+    fn import_state(&mut self, string: Option<&str>) -> Result<()> {
+        self.0.import_state(string)
+    }
+
+    fn export_state(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        let state = self.0.export_state()?;
+        write_str(str_buf, &state, self.0.limits().state_str, "state")
+    }
+
+    fn players_to_move(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        let to_move = self.0.players_to_move()?;
+        let max = self.0.limits().max_players_to_move;
+        write_slice(players, &to_move, max.into(), "players to move")
+    }
+
+    fn get_concrete_moves(
+        &mut self,
+        player: player_id,
+        moves: &mut PtrVec<move_code>,
+    ) -> Result<()> {
+        let concrete = self.0.get_concrete_moves(player)?;
+        let max = self.0.limits().max_moves;
+        write_slice(moves, &concrete, max as usize, "moves")
+    }
+
+    fn get_move_code(&mut self, player: player_id, string: &str) -> Result<move_code> {
+        self.0.get_move_code(player, string)
+    }
+
+    fn get_move_str(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        str_buf: &mut StrBuf,
+    ) -> Result<()> {
+        let string = self.0.get_move_str(player, mov)?;
+        write_str(str_buf, &string, self.0.limits().move_str, "move string")
+    }
+
+    fn make_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        _sync_ctr: &mut sync_counter,
+    ) -> Result<()> {
+        self.0.make_move(player, mov)
+    }
+
+    fn get_results(&mut self, players: &mut PtrVec<player_id>) -> Result<()> {
+        let results = self.0.get_results()?;
+        let max = self.0.limits().max_results;
+        write_slice(players, &results, max.into(), "results")
+    }
+
+    fn is_legal_move(
+        &mut self,
+        player: player_id,
+        mov: move_code,
+        _sync_ctr: sync_counter,
+    ) -> Result<()> {
+        self.0.is_legal_move(player, mov)
+    }
+
+    fn export_options(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        let options = self.0.export_options()?;
+        write_str(str_buf, &options, self.0.limits().options_str, "options")
+    }
+
+    fn print(&mut self, str_buf: &mut StrBuf) -> Result<()> {
+        let string = self.0.print()?;
+        write_str(str_buf, &string, self.0.limits().print_str, "print string")
+    }
+
+    fn serialize(&mut self, buf: &mut PtrVec<u8>) -> Result<()> {
+        let data = self.0.serialize()?;
+        let max = self.0.limits().serialize;
+        write_slice(buf, &data, max, "serialized data")
+    }
+
+    fn get_concrete_move_probabilities(
+        &mut self,
+        player: player_id,
+        probabilities: &mut PtrVec<f32>,
+    ) -> Result<()> {
+        let all = self.0.get_concrete_move_probabilities(player)?;
+        let max = self.0.limits().max_moves;
+        write_slice(probabilities, &all, max as usize, "probabilities")
+    }
+
+    fn get_random_move(&mut self, seed: u64) -> Result<move_code> {
+        self.0.get_random_move(seed)
+    }
+
+    fn discretize(&mut self, seed: u64) -> Result<()> {
+        self.0.discretize(seed)
+    }
+
+    fn get_move_ordering(
+        &mut self,
+        player: player_id,
+        moves: &mut PtrVec<move_code>,
+    ) -> Result<()> {
+        let ordered = self.0.get_move_ordering(player)?;
+        let max = self.0.limits().max_moves;
+        write_slice(moves, &ordered, max as usize, "ordered moves")
+    }
+
+    fn get_scores(&mut self, scores: &mut PtrVec<i32>) -> Result<()> {
+        let all = self.0.get_scores()?;
+        let max = self.0.limits().player_count;
+        write_slice(scores, &all, max.into(), "scores")
+    }
+
+    fn get_id(&mut self) -> Result<u64> {
+        self.0.get_id()
+    }
+
+    fn get_eval(&mut self, player: player_id) -> Result<f32> {
+        self.0.get_eval(player)
+    }
+}
+
+/// Copies `string` into `str_buf` if it is not longer than `max`.
+fn write_str(str_buf: &mut StrBuf, string: &str, max: usize, name: &str) -> Result<()> {
+    if string.len() > max.min(str_buf.capacity()) {
+        return Err(exceeded(name, string.len(), max));
+    }
+    str_buf.write_str(string).map_err(|_| {
+        Error::new_dynamic(
+            ErrorCode::StateCorrupted,
+            format!("{name} contains a NUL byte"),
+        )
+    })
+}
+
+/// Copies `items` into `buf` if there are not more than `max`.
+fn write_slice<T: Clone>(buf: &mut PtrVec<T>, items: &[T], max: usize, name: &str) -> Result<()> {
+    if items.len() > max.min(buf.capacity()) {
+        return Err(exceeded(name, items.len(), max));
+    }
+    buf.extend_from_slice(items);
+    Ok(())
+}
+
+fn exceeded(name: &str, len: usize, max: usize) -> Error {
+    Error::new_dynamic(
+        ErrorCode::StateCorrupted,
+        format!("{name} of length {len} exceeds the declared maximum of {max}"),
+    )
+}
@@ -0,0 +1,504 @@
+//! Inference of [`buf_sizer`] values by exploring a game.
+//!
+//! [`explore()`] calls the [`GameMethods`] of a game directly with buffers
+//! which grow as needed and records the largest outputs.
+//! The resulting [`Report`] compares them to the declared [`buf_sizer`].
+//!
+//! Growing a buffer requires calling the method again.
+//! Panics caused by full buffers, e.g., from `write!(...).expect(...)`, are
+//! caught for this, but still printed by the panic hook.
+//!
+//! # Example
+//! ```ignore
+//! let mut features = game_feature_flags::default();
+//! features.set_print(true);
+//! let report = explore::<MyGame>(&GameInit::Default, features, &Exploration::default())?;
+//! println!("{report}");
+//! assert!(report.too_small().is_empty());
+//! ```
+
+use crate::{
+    buf_sizer, game_feature_flags, move_code, player_id, ptr_vec::Storage, rng::Rng, Error,
+    ErrorCode, GameInit, GameMethods, PtrVec, Result, SYNC_COUNTER_DEFAULT,
+};
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    num::NonZeroU8,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+};
+
+/// Initial capacity of the buffers.
+const INITIAL_CAPACITY: usize = 64;
+/// Buffers are not grown beyond this capacity.
+const MAX_CAPACITY: usize = 1 << 24;
+
+/// How [`explore()`] visits the states of a game.
+#[derive(Clone, Debug)]
+pub enum Exploration {
+    /// Visits all states reachable from the initial one in breadth-first
+    /// order, but at most `max_states`.
+    ///
+    /// States are considered equal if their exported states are equal.
+    Exhaustive { max_states: usize },
+    /// Plays `playouts` random playouts of at most `max_moves` each.
+    Sampling {
+        playouts: usize,
+        max_moves: usize,
+        seed: u64,
+    },
+}
+
+impl Default for Exploration {
+    fn default() -> Self {
+        Self::Exhaustive {
+            max_states: 100_000,
+        }
+    }
+}
+
+/// Result of [`explore()`].
+#[derive(Clone, Debug)]
+pub struct Report {
+    /// The [`buf_sizer`] returned by [`GameMethods::create()`].
+    pub declared: buf_sizer,
+    /// The maxima observed during exploration.
+    ///
+    /// Only `options_str`, `state_str`, `max_players_to_move`, `max_moves`,
+    /// `max_results`, `move_str`, and `print_str` are inferred.
+    /// String sizes include the NUL byte.
+    pub observed: buf_sizer,
+    /// Number of visited states.
+    pub states: usize,
+    /// Whether all reachable states have been visited.
+    pub complete: bool,
+}
+
+impl Report {
+    /// Returns the names of the [`buf_sizer`] fields which are smaller than
+    /// observed.
+    pub fn too_small(&self) -> Vec<&'static str> {
+        self.fields()
+            .into_iter()
+            .filter(|(_, declared, observed)| declared < observed)
+            .map(|(name, _, _)| name)
+            .collect()
+    }
+
+    fn fields(&self) -> [(&'static str, u64, u64); 7] {
+        let (d, o) = (&self.declared, &self.observed);
+        [
+            ("options_str", d.options_str as u64, o.options_str as u64),
+            ("state_str", d.state_str as u64, o.state_str as u64),
+            (
+                "max_players_to_move",
+                d.max_players_to_move.into(),
+                o.max_players_to_move.into(),
+            ),
+            ("max_moves", d.max_moves.into(), o.max_moves.into()),
+            ("max_results", d.max_results.into(), o.max_results.into()),
+            ("move_str", d.move_str as u64, o.move_str as u64),
+            ("print_str", d.print_str as u64, o.print_str as u64),
+        ]
+    }
+}
+
+impl fmt::Display for Report {
+    /// Lists the declared and observed value of each field.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let coverage = if self.complete {
+            "complete"
+        } else {
+            "incomplete"
+        };
+        writeln!(f, "{} states visited ({coverage})", self.states)?;
+        for (name, declared, observed) in self.fields() {
+            write!(f, "{name}: declared {declared}, observed {observed}")?;
+            if declared < observed {
+                write!(f, " (too small)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Explores game `G` created from `init_info` and reports the largest
+/// outputs.
+///
+/// `features` determine whether `export_options` and `print` are measured.
+/// Errors returned by the game abort the exploration.
+pub fn explore<G: GameMethods>(
+    init_info: &GameInit,
+    features: game_feature_flags,
+    exploration: &Exploration,
+) -> Result<Report> {
+    let (mut game, declared) = G::create(init_info)?;
+    let mut explorer = Explorer {
+        features,
+        observed: buf_sizer::default(),
+        str_buf: Storage::new(INITIAL_CAPACITY),
+        players: Storage::new(INITIAL_CAPACITY),
+        moves: Storage::new(INITIAL_CAPACITY),
+        states: 0,
+    };
+    if features.options() {
+        let size = explorer.measure_str(|buf| game.export_options(buf))?.len() + 1;
+        explorer.observed.options_str = size;
+    }
+
+    let complete = match *exploration {
+        Exploration::Exhaustive { max_states } => explorer.exhaustive(game, max_states)?,
+        Exploration::Sampling {
+            playouts,
+            max_moves,
+            seed,
+        } => {
+            explorer.sampling(game, playouts, max_moves, seed)?;
+            false
+        }
+    };
+
+    Ok(Report {
+        declared,
+        observed: explorer.observed,
+        states: explorer.states,
+        complete,
+    })
+}
+
+struct Explorer {
+    features: game_feature_flags,
+    observed: buf_sizer,
+    str_buf: Storage<NonZeroU8>,
+    players: Storage<player_id>,
+    moves: Storage<move_code>,
+    states: usize,
+}
+
+impl Explorer {
+    /// Returns whether all states have been visited.
+    fn exhaustive<G: GameMethods>(&mut self, mut game: G, max_states: usize) -> Result<bool> {
+        let initial = self.measure_str(|buf| game.export_state(buf))?.to_string();
+        let mut seen = HashSet::from([initial]);
+        let mut queue = VecDeque::from([game]);
+
+        while let Some(mut game) = queue.pop_front() {
+            for (player, moves) in self.visit(&mut game)? {
+                for mov in moves {
+                    let mut child = game.clone();
+                    let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+                    child.make_move(player, mov, &mut sync_ctr)?;
+                    let state = self.measure_str(|buf| child.export_state(buf))?;
+                    if seen.contains(state) {
+                        continue;
+                    }
+                    if seen.len() >= max_states {
+                        return Ok(false);
+                    }
+                    seen.insert(state.to_string());
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn sampling<G: GameMethods>(
+        &mut self,
+        game: G,
+        playouts: usize,
+        max_moves: usize,
+        seed: u64,
+    ) -> Result<()> {
+        let mut rng = Rng::new(seed);
+        for _ in 0..playouts {
+            let mut game = game.clone();
+            let mut sync_ctr = SYNC_COUNTER_DEFAULT;
+            for _ in 0..max_moves {
+                let options = self.visit(&mut game)?;
+                if options.is_empty() {
+                    break;
+                }
+                let (player, moves) = &options[rng.index(options.len())];
+                let mov = moves[rng.index(moves.len())];
+                game.make_move(*player, mov, &mut sync_ctr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Measures all outputs of `game` and returns the moves of each player to
+    /// move.
+    fn visit<G: GameMethods>(&mut self, game: &mut G) -> Result<Vec<(player_id, Vec<move_code>)>> {
+        self.states += 1;
+        let observed = &mut self.observed;
+
+        let size = measure_str(&mut self.str_buf, |buf| game.export_state(buf))?.len() + 1;
+        observed.state_str = observed.state_str.max(size);
+        if self.features.print() {
+            let size = measure_str(&mut self.str_buf, |buf| game.print(buf))?.len() + 1;
+            observed.print_str = observed.print_str.max(size);
+        }
+
+        let results = measure(&mut self.players, |buf| game.get_results(buf))?.len();
+        observed.max_results = observed
+            .max_results
+            .max(results.try_into().unwrap_or(u8::MAX));
+
+        let players = measure(&mut self.players, |buf| game.players_to_move(buf))?.to_vec();
+        observed.max_players_to_move = observed
+            .max_players_to_move
+            .max(players.len().try_into().unwrap_or(u8::MAX));
+
+        let mut options = Vec::with_capacity(players.len());
+        for player in players {
+            let moves = measure(&mut self.moves, |buf| game.get_concrete_moves(player, buf))?;
+            observed.max_moves = observed
+                .max_moves
+                .max(moves.len().try_into().unwrap_or(u32::MAX));
+            let moves = moves.to_vec();
+            for &mov in &moves {
+                let string =
+                    measure_str(&mut self.str_buf, |buf| game.get_move_str(player, mov, buf))?;
+                observed.move_str = observed.move_str.max(string.len() + 1);
+            }
+            if !moves.is_empty() {
+                options.push((player, moves));
+            }
+        }
+        Ok(options)
+    }
+
+    fn measure_str(&mut self, f: impl FnMut(&mut PtrVec<NonZeroU8>) -> Result<()>) -> Result<&str> {
+        measure_str(&mut self.str_buf, f)
+    }
+}
+
+/// Calls `f` with a buffer from `storage`, growing it until the output fits.
+///
+/// A buffer filled up to its capacity might have been too small, so `f` is
+/// called again with a larger buffer when it fails, panics, or fills it.
+fn measure<T>(
+    storage: &mut Storage<T>,
+    mut f: impl FnMut(&mut PtrVec<T>) -> Result<()>,
+) -> Result<&[T]> {
+    loop {
+        let result = catch_unwind(AssertUnwindSafe(|| f(&mut storage.get_ptr_vec())));
+        let full = storage.len() >= storage.capacity();
+        if full && storage.capacity() < MAX_CAPACITY {
+            *storage = Storage::new(storage.capacity() * 2);
+            continue;
+        }
+        match result {
+            Ok(Ok(())) if !full => return Ok(storage),
+            Ok(Ok(())) => {
+                return Err(Error::new_dynamic(
+                    ErrorCode::OutOfMemory,
+                    format!("output exceeds {MAX_CAPACITY} items"),
+                ))
+            }
+            Ok(Err(error)) => return Err(error),
+            Err(panic) => resume_unwind(panic),
+        }
+    }
+}
+
+fn measure_str(
+    storage: &mut Storage<NonZeroU8>,
+    f: impl FnMut(&mut PtrVec<NonZeroU8>) -> Result<()>,
+) -> Result<&str> {
+    measure(storage, f)?;
+    storage.as_str().map_err(|e| {
+        Error::new_dynamic(
+            ErrorCode::StateCorrupted,
+            format!("game wrote invalid UTF-8: {e}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        simple::{Limits, Simple, SimpleGame},
+        sys,
+    };
+
+    /// The only player counts down from twelve by one or two.
+    #[derive(Clone, PartialEq, Eq)]
+    struct Countdown(u8);
+
+    impl SimpleGame for Countdown {
+        fn create(_init_info: &GameInit) -> Result<Self> {
+            Ok(Self(12))
+        }
+
+        fn limits(&self) -> Limits {
+            Limits {
+                player_count: 1,
+                max_players_to_move: 1,
+                max_moves: 2,
+                max_results: 1,
+                state_str: 2,
+                move_str: 1,
+                ..Default::default()
+            }
+        }
+
+        fn import_state(&mut self, state: Option<&str>) -> Result<()> {
+            self.0 = state
+                .unwrap_or("12")
+                .parse()
+                .map_err(|_| Error::new_static(ErrorCode::InvalidState, "invalid state\0"))?;
+            Ok(())
+        }
+
+        fn export_state(&self) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn players_to_move(&self) -> Result<Vec<player_id>> {
+            Ok(if self.0 > 0 { vec![1] } else { vec![] })
+        }
+
+        fn get_concrete_moves(&self, _player: player_id) -> Result<Vec<move_code>> {
+            Ok((1..=self.0.min(2)).map(Into::into).collect())
+        }
+
+        fn get_move_str(&self, _player: player_id, mov: move_code) -> Result<String> {
+            Ok(mov.to_string())
+        }
+
+        fn make_move(&mut self, _player: player_id, mov: move_code) -> Result<()> {
+            self.0 -= mov as u8;
+            Ok(())
+        }
+
+        fn get_results(&self) -> Result<Vec<player_id>> {
+            Ok(if self.0 == 0 { vec![1] } else { vec![] })
+        }
+    }
+
+    fn explore_countdown(exploration: &Exploration) -> Report {
+        let features = game_feature_flags::default();
+        explore::<Simple<Countdown>>(&GameInit::Default, features, exploration).unwrap()
+    }
+
+    #[test]
+    fn exhaustive_visits_all_states() {
+        let report = explore_countdown(&Exploration::default());
+        assert!(report.complete);
+        assert_eq!(13, report.states);
+        let observed = report.observed;
+        assert_eq!(3, observed.state_str);
+        assert_eq!(1, observed.max_players_to_move);
+        assert_eq!(2, observed.max_moves);
+        assert_eq!(1, observed.max_results);
+        assert_eq!(2, observed.move_str);
+        // Disabled features are not measured.
+        assert_eq!(0, observed.options_str);
+        assert_eq!(0, observed.print_str);
+        assert!(report.too_small().is_empty(), "{report}");
+    }
+
+    #[test]
+    fn exhaustive_stops_at_max_states() {
+        let report = explore_countdown(&Exploration::Exhaustive { max_states: 5 });
+        assert!(!report.complete);
+        assert!(report.states <= 5);
+    }
+
+    #[test]
+    fn sampling_is_incomplete() {
+        let report = explore_countdown(&Exploration::Sampling {
+            playouts: 3,
+            max_moves: 100,
+            seed: 42,
+        });
+        assert!(!report.complete);
+        assert!(report.states >= 3 * 7);
+        assert_eq!(2, report.observed.max_moves);
+    }
+
+    #[test]
+    fn report_lists_small_fields() {
+        let report = Report {
+            declared: buf_sizer {
+                state_str: 3,
+                max_moves: 2,
+                ..Default::default()
+            },
+            observed: buf_sizer {
+                state_str: 4,
+                max_moves: 2,
+                move_str: 2,
+                ..Default::default()
+            },
+            states: 7,
+            complete: false,
+        };
+        assert_eq!(["state_str", "move_str"], *report.too_small());
+        let text = report.to_string();
+        assert!(text.starts_with("7 states visited (incomplete)\n"));
+        assert!(text.contains("state_str: declared 3, observed 4 (too small)\n"));
+        assert!(text.contains("max_moves: declared 2, observed 2\n"));
+    }
+
+    #[test]
+    fn measure_grows_full_buffers() {
+        let mut storage = Storage::new(INITIAL_CAPACITY);
+        let output = measure(&mut storage, |buf| {
+            // Fills the buffer without panicking.
+            for i in 0..100 {
+                if buf.is_full() {
+                    break;
+                }
+                buf.push(i);
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((0..100).collect::<Vec<_>>(), output);
+        assert_eq!(2 * INITIAL_CAPACITY, storage.capacity());
+    }
+
+    #[test]
+    fn measure_retries_panics_of_full_buffers() {
+        let mut storage = Storage::new(INITIAL_CAPACITY);
+        let output = measure(&mut storage, |buf| {
+            buf.extend(0..100);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(100, output.len());
+    }
+
+    #[test]
+    fn measure_returns_errors() {
+        let mut storage = Storage::<u8>::new(INITIAL_CAPACITY);
+        let error = measure(&mut storage, |_| {
+            Err(Error::new_static(ErrorCode::InvalidInput, "failed\0"))
+        })
+        .unwrap_err();
+        let expected: sys::error_code = ErrorCode::InvalidInput.into();
+        let code: sys::error_code = error.code.into();
+        assert_eq!(expected, code);
+        assert_eq!(INITIAL_CAPACITY, storage.capacity());
+    }
+
+    #[test]
+    fn measure_str_rejects_invalid_utf8() {
+        let mut storage = Storage::new(INITIAL_CAPACITY);
+        let error = measure_str(&mut storage, |buf| {
+            buf.push(NonZeroU8::new(0xff).unwrap());
+            Ok(())
+        })
+        .unwrap_err();
+        let expected: sys::error_code = ErrorCode::StateCorrupted.into();
+        let code: sys::error_code = error.code.into();
+        assert_eq!(expected, code);
+    }
+}
@@ -0,0 +1,220 @@
+//! Conformance tests for [`GameMethods`] implementations.
+//!
+//! [`check()`] drives a game through its exported `*_wrapped` functions, just
+//! like _surena_ would, using buffers sized according to the
+//! [`buf_sizer`](crate::buf_sizer).
+//! Along many random playouts, it verifies invariants which are easy to break
+//! by accident, e.g., that [`GameMethods::export_state`] and
+//! [`GameMethods::import_state`] round-trip.
+//!
+//! # Example
+//! ```ignore
+//! #[test]
+//! fn conformance() {
+//!     let violations = check::<MyGame>(metadata(), &GameInit::Default, &Config::default());
+//!     let messages: Vec<_> = violations.iter().map(ToString::to_string).collect();
+//!     assert!(violations.is_empty(), "{}", messages.join("\n"));
+//! }
+//! ```
+
+use crate::{
+    create_game_methods, describe, game_methods, host::Game, move_code, player_id, rng::Rng, Error,
+    GameInit, GameMethods, Metadata,
+};
+
+use std::fmt;
+
+/// Configuration for [`check()`].
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Number of random playouts.
+    pub playouts: usize,
+    /// Maximum number of moves per playout.
+    pub max_moves: usize,
+    /// Seed from which the seeds of the individual playouts are derived.
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            playouts: 100,
+            max_moves: 1000,
+            seed: 0,
+        }
+    }
+}
+
+/// A broken invariant found by [`check()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Seed of the playout which found the violation.
+    pub seed: u64,
+    /// Moves leading from the initial state to the offending state.
+    pub moves: Vec<(player_id, move_code)>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    /// Shows the message and the moves as `player:move` pairs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (seed {}, moves [", self.message, self.seed)?;
+        for (i, (player, mov)) in self.moves.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{player}:{mov}")?;
+        }
+        write!(f, "])")
+    }
+}
+
+/// Runs [`Config::playouts`] random playouts of game `G` and returns all
+/// violations found.
+///
+/// Each playout stops at its first violation.
+/// The checked invariants are:
+/// - the game can be created from `init_info`,
+/// - exporting and importing the state round-trips and compares equal,
+/// - clones and copies compare equal,
+/// - all concrete moves are legal and their strings round-trip,
+/// - no output exceeds the [`buf_sizer`](crate::buf_sizer) and no call panics.
+pub fn check<G: GameMethods>(
+    metadata: Metadata,
+    init_info: &GameInit,
+    config: &Config,
+) -> Vec<Violation> {
+    let methods = create_game_methods::<G>(metadata);
+    let mut seeds = Rng::new(config.seed);
+
+    let mut violations = vec![];
+    for _ in 0..config.playouts {
+        let seed = seeds.next_u64();
+        let mut moves = vec![];
+        if let Err(message) = playout(&methods, init_info, seed, config.max_moves, &mut moves) {
+            violations.push(Violation {
+                seed,
+                moves,
+                message,
+            });
+        }
+    }
+    violations
+}
+
+/// Plays random moves until the game is over and records them in `moves`.
+fn playout(
+    methods: &game_methods,
+    init_info: &GameInit,
+    seed: u64,
+    max_moves: usize,
+    moves: &mut Vec<(player_id, move_code)>,
+) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut game = Game::new(methods, init_info).map_err(context("create"))?;
+    // Used as target for importing and copying.
+    let mut scratch = Game::new(methods, init_info).map_err(context("create"))?;
+
+    for _ in 0..max_moves {
+        check_state(&mut game, &mut scratch)?;
+
+        let players = game
+            .players_to_move()
+            .map_err(context("players_to_move"))?
+            .to_vec();
+        if players.is_empty() {
+            game.get_results().map_err(context("get_results"))?;
+            return Ok(());
+        }
+
+        let mut options = Vec::with_capacity(players.len());
+        for &player in &players {
+            let concrete = game
+                .get_concrete_moves(player)
+                .map_err(context("get_concrete_moves"))?
+                .to_vec();
+            if concrete.is_empty() {
+                return Err(format!("player {player} is to move but has no moves"));
+            }
+            for &mov in &concrete {
+                check_move(&mut game, player, mov)?;
+            }
+            options.push(concrete);
+        }
+
+        let i = rng.index(players.len());
+        let (player, concrete) = (players[i], &options[i]);
+        let mov = concrete[rng.index(concrete.len())];
+        moves.push((player, mov));
+        game.make_move(player, mov).map_err(context("make_move"))?;
+    }
+
+    Ok(())
+}
+
+/// Checks the invariants which do not depend on moves.
+fn check_state(game: &mut Game, scratch: &mut Game) -> Result<(), String> {
+    let state = game
+        .export_state()
+        .map_err(context("export_state"))?
+        .to_string();
+    scratch
+        .import_state(Some(&state))
+        .map_err(context("import_state"))?;
+    let imported = scratch.export_state().map_err(context("export_state"))?;
+    if imported != state {
+        return Err(format!(
+            "state \"{state}\" was exported as \"{imported}\" after importing"
+        ));
+    }
+    if !scratch.compare(game).map_err(context("compare"))? {
+        return Err(format!("state \"{state}\" differs after importing"));
+    }
+
+    let mut clone = game.try_clone().map_err(context("clone"))?;
+    if !clone.compare(game).map_err(context("compare"))? {
+        return Err("clone differs from original".to_string());
+    }
+    scratch.copy_from(game).map_err(context("copy_from"))?;
+    if !scratch.compare(game).map_err(context("compare"))? {
+        return Err("copy differs from original".to_string());
+    }
+
+    let features = game.methods().features;
+    if features.options() {
+        game.export_options().map_err(context("export_options"))?;
+    }
+    if features.print() {
+        game.print().map_err(context("print"))?;
+    }
+    Ok(())
+}
+
+/// Checks that `mov` is legal and that its string round-trips.
+fn check_move(game: &mut Game, player: player_id, mov: move_code) -> Result<(), String> {
+    if let Err(error) = game.is_legal_move(player, mov) {
+        return Err(format!(
+            "concrete move {mov} of player {player} is illegal: {}",
+            describe(&error)
+        ));
+    }
+    let string = game
+        .get_move_str(player, mov)
+        .map_err(context("get_move_str"))?
+        .to_string();
+    let parsed = game
+        .get_move_code(player, &string)
+        .map_err(context("get_move_code"))?;
+    if parsed != mov {
+        return Err(format!(
+            "move {mov} of player {player} was printed as \"{string}\" but parsed as {parsed}"
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a closure turning an [`Error`] of the function `name` into a
+/// message.
+fn context(name: &'static str) -> impl Fn(Error) -> String {
+    move |error| format!("{name} failed: {}", describe(&error))
+}